@@ -14,7 +14,7 @@
 use adnl::{
     common::TaggedTlObject, client::{AdnlClient, AdnlClientConfig, AdnlClientConfigJson}
 };
-use std::{convert::TryInto, env, str::FromStr, time::Duration};
+use std::{convert::TryInto, env, str::FromStr, sync::Arc, time::Duration};
 use ton_api::{
     serialize_boxed,
     ton::{
@@ -26,10 +26,12 @@ use ton_api::{
 #[cfg(feature = "telemetry")]
 use ton_api::tag_from_bare_object;
 use ton_block::{
-    AccountStatus, Deserializable, BlockIdExt, Serializable, ShardAccount
+    Account, AccountStatus, Block, ConfigParamEnum, ConfigParams, Deserializable, BlockIdExt, ExternalInboundMessageHeader,
+    Grams, MerkleProof, Message, MsgAddressInt, Serializable, ShardAccount, ShardStateUnsplit, SHARD_FULL
 };
 use ton_types::{
-    error, fail, Result, BuilderData, Ed25519KeyOption, SliceData, UInt256, write_boc
+    error, fail, Result, BuilderData, Cell, CellType, Ed25519KeyOption, HashmapE, HashmapType, KeyOption,
+    SliceData, UInt256, read_boc, read_single_root_boc, write_boc
 };
 
 include!("../common/src/test.rs");
@@ -39,9 +41,9 @@ trait SendReceive {
     fn receive<Q: ToString>(
         answer: TLObject, 
         _params: impl Iterator<Item = Q>
-    ) -> Result<(String, Vec<u8>)> {
+    ) -> Result<(serde_json::Value, Vec<u8>)> {
         downcast::<ton_api::ton::engine::validator::Success>(answer)?;
-        Ok(("success".to_string(), vec![]))
+        Ok((serde_json::json!({"status": "success"}), vec![]))
     }
 }
 
@@ -59,23 +61,24 @@ macro_rules! commands {
                 fn help() -> &'static str {$help}
             }
         )*
-        fn _command_help(name: &str) -> Result<&str> {
+        const COMMANDS: &[&str] = &[$($name, )*];
+        fn command_help(name: &str) -> Result<&str> {
             match name {
                 $($name => Ok($command::help()), )*
-                _ => fail!("command {} not supported", name)
+                _ => Err(ConsoleError::Params(format!("command {} not supported", name)).into())
             }
         }
         fn command_send<Q: ToString>(name: &str, params: impl Iterator<Item = Q>) -> Result<TLObject> {
             match name {
                 $($name => $command::send(params), )*
-                _ => fail!("command {} not supported", name)
+                _ => Err(ConsoleError::Params(format!("command {} not supported", name)).into())
             }
         }
         fn command_receive<Q: ToString>(
             name: &str,
             answer: TLObject,
             params: impl Iterator<Item = Q>
-        ) -> Result<(String, Vec<u8>)> {
+        ) -> Result<(serde_json::Value, Vec<u8>)> {
             match name {
                 $($name => $command::receive(answer, params), )*
                 _ => fail!("an error occured while receiving a response (command: {})", name)
@@ -90,26 +93,185 @@ commands! {
     AddValidatorPermKey, "addpermkey", "addpermkey <keyhash> <election-date> <expire-at>\tadd validator permanent key"
     AddValidatorTempKey, "addtempkey", "addtempkey <permkeyhash> <keyhash> <expire-at>\tadd validator temp key"
     Bundle, "bundle", "bundle <block_id>\tprepare bundle"
+    DelAdnlAddr, "deladnl", "deladnl <keyhash>\tdelete ADNL addr"
+    DelValidatorAdnlAddr, "delvalidatoraddr", "delvalidatoraddr <permkeyhash> <keyhash>\tdelete validator ADNL addr"
+    DelValidatorPermKey, "delpermkey", "delpermkey <keyhash>\tdelete validator permanent key"
+    DelValidatorTempKey, "deltempkey", "deltempkey <permkeyhash> <keyhash>\tdelete validator temp key"
     ExportPub, "exportpub", "exportpub <keyhash>\texports public key by key hash"
     FutureBundle, "future_bundle", "future_bundle <block_id>\tprepare future bundle"
-    GetAccount, "getaccount", "getaccount <account id> <Option<file name>>\tget account info"
+    GetAccount, "getaccount", "getaccount <account id> <Option<file name>> [--boc] [--decode] [--block <block id|mc seqno>] [--verify] [--trusted-block <block id>]\tget account info"
     GetAccountState, "getaccountstate", "getaccountstate <account id> <file name>\tsave accountstate to file"
-    GetBlockchainConfig, "getblockchainconfig", "getblockchainconfig\tget current config from masterchain state"
-    GetConfig, "getconfig", "getconfig <param_number>\tget current config param from masterchain state"
+    GetBlockchainConfig, "getblockchainconfig", "getblockchainconfig [--block <block id|mc seqno>] [--save <file name>] [--verify] [--trusted-block <block id>]\tget current config from masterchain state"
+    GetConfig, "getconfig", "getconfig <param_number> [--block <block id|mc seqno>] [--save <file name>] [--verify] [--trusted-block <block id>]\tget current config param from masterchain state"
     GetSessionStats, "getconsensusstats", "getconsensusstats\tget consensus statistics for the node"
     GetSelectedStats, "getstatsnew", "getstatsnew\tget status full node or validator in new format"
-    GetStats, "getstats", "getstats\tget status full node or validator"
+    GetStats, "getstats", "getstats [--watch <seconds> [--max-lag <seconds>] [--expect-validator] [--hook <command>]]\tget status full node or validator"
+    ImportKey, "importkey", "importkey <private key in hex|base64 or @keys.json>\timport ed25519 private key to server"
     NewKeypair, "newkey", "newkey\tgenerates new key pair on server"
     SendMessage, "sendmessage", "sendmessage <filename>\tload a serialized message from <filename> and send it to server"
     SetStatesGcInterval, "setstatesgcinterval", "setstatesgcinterval <milliseconds>\tset interval in <milliseconds> between shard states GC runs"
-    Sign, "sign", "sign <keyhash> <data>\tsigns bytestring with privkey"
+    Sign, "sign", "sign <keyhash> <data in hex|base64 or @file> [--boc] [--format hex|base64] [--out <file name>]\tsigns bytestring or boc hash with privkey"
+}
+
+macro_rules! local_commands {
+    ($($name: literal $(| $alias: literal)*, $method: ident, $help: literal)*) => {
+        const LOCAL_COMMANDS: &[(&[&str], &str)] = &[$((&[$name $(, $alias)*], $help), )*];
+        impl ControlClient {
+            async fn command_local(
+                &mut self,
+                name: &str,
+                params: std::slice::Iter<'_, String>
+            ) -> Option<Result<(serde_json::Value, Vec<u8>)>> {
+                match name {
+                    $($name $(| $alias)* => Some(self.$method(params).await), )*
+                    _ => None
+                }
+            }
+        }
+    };
+}
+
+// commands processed by console itself, aliases are given after the name
+local_commands! {
+    "config_diff" | "configdiff", process_config_diff, "configdiff <block id|mc seqno|last> <block id|mc seqno|last|config.json> [param_number...]\tprint difference between two configs"
+    "config_param" | "cparam", process_config_param, "cparam <index> <zerostate.json> <Option<file name>>\textract config param from zerostate to boc"
+    "config_proposal" | "cproposal", process_config_proposal, "cproposal <index> <param.json> <expire_at> <Option<file name>> [--critical] [--if-hash-equal <hash>] [--value <nanotokens> [--msg-file <file name>] [--send]]\tprepare config proposal"
+    "config_proposals" | "cproposals", process_config_proposals, "cproposals\tlist active config proposals"
+    "config_vote" | "cvote", process_config_vote, "cvote <keyhash> <proposal hash> <Option<file name>> [--send]\tvote for config proposal"
+    "election-bid" | "election_bid" | "ebid", process_election_bid, "ebid <elect-time expire-time|auto> <Option<file name>> [--dry-run] [--perm-key <keyhash>] [--adnl-key <keyhash>] [--stake <nanotokens> [--msg-file <file name>] [--send]]\tprepare election bid"
+    "elections", process_elections, "elections <Option<wallet address>>\tprint election report"
+    "help", process_help, "help <Option<command>>\tprint help for all commands or for the given one"
+    "keys", process_keys, "keys [--delete-expired]\tlist validator keys of server with their expire time"
+    "keys_backup" | "backupkeys", process_keys_backup, "keys_backup <file name>\tsave private keys of server encrypted with password"
+    "keys_restore" | "restorekeys", process_keys_restore, "keys_restore <file name>\timport keys from backup and bind them again"
+    "recover_stake", process_recover_stake, "recover_stake <Option<file name>> [--check [--force]] [--value <nanotokens> [--msg-file <file name>] [--send]]\tprepare stake recovery request"
+    "rotate", process_rotate, "rotate [--dry-run] [--margin <seconds>]\tadd fresh temp keys and ADNL addresses for active and next validator keys"
+}
+
+// control query commands which change nothing in the node and may be repeated
+const READ_COMMANDS: &[&str] = &[
+    "exportpub", "getaccount", "getaccountstate", "getblockchainconfig", "getconfig",
+    "getconsensusstats", "getstats", "getstatsnew"
+];
+
+// help for all commands or for the given one
+fn help(name: Option<&str>) -> Result<String> {
+    match name {
+        Some(name) => match LOCAL_COMMANDS.iter().find(|(names, _)| names.contains(&name)) {
+            Some((_, help)) => Ok(help.to_string()),
+            None => Ok(command_help(name)?.to_string())
+        }
+        None => {
+            let mut lines = LOCAL_COMMANDS.iter().map(|(_, help)| *help)
+                .chain(COMMANDS.iter().filter_map(|name| command_help(name).ok()))
+                .collect::<Vec<_>>();
+            lines.sort_unstable();
+            Ok(lines.join("\n"))
+        }
+    }
+}
+
+// names of all commands with aliases for completion
+fn command_names() -> impl Iterator<Item = &'static str> {
+    LOCAL_COMMANDS.iter().flat_map(|(names, _)| names.iter().copied())
+        .chain(COMMANDS.iter().copied())
+        .chain(["quit"].iter().copied())
+}
+
+/// Classified console errors, the class is reported in json output
+#[derive(Debug, failure::Fail)]
+enum ConsoleError {
+    #[fail(display = "{}", 0)]
+    Params(String),
+    #[fail(display = "Error receiving answer: {}", 0)]
+    Connection(String),
+    #[fail(display = "Error response to {}: {} (code {})", query, message, code)]
+    ControlQuery {
+        query: String,
+        code: i32,
+        message: String
+    },
+    #[fail(display = "proof invalid: {}", 0)]
+    ProofInvalid(String),
+    #[fail(display = "timeout: {}", 0)]
+    Timeout(String),
+    #[fail(display = "config error: {}", 0)]
+    Config(String),
+    #[fail(display = "alert: {}", 0)]
+    Alert(String)
+}
+
+impl ConsoleError {
+    const OTHER: i32 = 1;
+
+    fn code(&self) -> i32 {
+        match self {
+            ConsoleError::Params(_) => 2,
+            ConsoleError::Connection(_) => 3,
+            ConsoleError::ControlQuery { .. } => 4,
+            ConsoleError::ProofInvalid(_) => 5,
+            ConsoleError::Timeout(_) => 6,
+            ConsoleError::Config(_) => 7,
+            ConsoleError::Alert(_) => 8
+        }
+    }
+
+    fn class(&self) -> &'static str {
+        match self {
+            ConsoleError::Params(_) => "params",
+            ConsoleError::Connection(_) => "connection",
+            ConsoleError::ControlQuery { .. } => "control_query",
+            ConsoleError::ProofInvalid(_) => "proof",
+            ConsoleError::Timeout(_) => "timeout",
+            ConsoleError::Config(_) => "config",
+            ConsoleError::Alert(_) => "alert"
+        }
+    }
+}
+
+fn error_to_json(err: &failure::Error) -> serde_json::Value {
+    let mut json = serde_json::json!({
+        "code": ConsoleError::OTHER,
+        "class": "other",
+        "message": err.to_string(),
+    });
+    if let Some(console_err) = err.downcast_ref::<ConsoleError>() {
+        json["code"] = console_err.code().into();
+        json["class"] = console_err.class().into();
+        if let ConsoleError::ControlQuery { code, .. } = console_err {
+            json["node_code"] = (*code).into();
+        }
+    }
+    json
+}
+
+// prints one json object per command in json mode, or description otherwise
+fn print_result(command: &str, result: &Result<(serde_json::Value, Vec<u8>)>, json: bool) {
+    if json {
+        let name = command.split_whitespace().next().unwrap_or_default();
+        let output = match result {
+            Ok((result, _)) => serde_json::json!({"command": name, "ok": true, "result": result}),
+            Err(err) => serde_json::json!({"command": name, "ok": false, "error": error_to_json(err)})
+        };
+        println!("{}", output);
+    } else {
+        match result {
+            Ok((serde_json::Value::String(result), _)) => println!("{}", result),
+            Ok((result, _)) => println!("{:#}", result),
+            Err(err) => println!("Error executing command: {}", err)
+        }
+    }
+}
+
+fn error_code(err: &failure::Error) -> i32 {
+    err.downcast_ref::<ConsoleError>().map_or(ConsoleError::OTHER, |err| err.code())
 }
 
 fn parse_any<A, Q: ToString>(param_opt: Option<Q>, name: &str, parse_value: impl FnOnce(&str) -> Result<A>) -> Result<A> {
     param_opt
         .ok_or_else(|| error!("insufficient parameters"))
         .and_then(|value| parse_value(value.to_string().trim_matches('\"')))
-        .map_err(|err| error!("you must give {}: {}", name, err))
+        .map_err(|err| ConsoleError::Params(format!("you must give {}: {}", name, err)).into())
 }
 
 fn downcast<T: ton_api::AnyBoxedSerialize>(data: TLObject) -> Result<T> {
@@ -119,11 +281,23 @@ fn downcast<T: ton_api::AnyBoxedSerialize>(data: TLObject) -> Result<T> {
     }
 }
 
+// hex is preferred if data is both valid hex and base64
 fn parse_data<Q: ToString>(param_opt: Option<Q>, name: &str) -> Result<ton::bytes> {
     parse_any(
         param_opt,
-        &format!("{} in hex format", name),
-        |value| Ok(ton::bytes(hex::decode(value)?))
+        &format!("{} in hex or base64 format or @<file name>", name),
+        |value| {
+            let data = match value.strip_prefix('@') {
+                Some(path) => std::fs::read(path)
+                    .map_err(|err| error!("Can't read file {}: {}", path, err))?,
+                None => match hex::decode(value) {
+                    Ok(data) => data,
+                    Err(_) => base64::decode(value)
+                        .map_err(|_| error!("{} is neither hex nor base64", value))?
+                }
+            };
+            Ok(ton::bytes(data))
+        }
     )
 }
 
@@ -146,10 +320,105 @@ fn parse_int<Q: ToString>(param_opt: Option<Q>, name: &str) -> Result<ton::int>
     parse_any(param_opt, name, |value| Ok(ton::int::from_str(value)?))
 }
 
+// removes flag from params returning whether it was present
+fn extract_flag(params: &mut Vec<String>, names: &[&str]) -> bool {
+    let len = params.len();
+    params.retain(|param| !names.contains(&param.as_str()));
+    params.len() != len
+}
+
+// removes option with its value from params
+fn extract_option(params: &mut Vec<String>, name: &str) -> Result<Option<String>> {
+    match params.iter().position(|param| param == name) {
+        None => Ok(None),
+        Some(index) => {
+            if index + 1 >= params.len() {
+                fail!("option {} requires a value", name)
+            }
+            let value = params.remove(index + 1);
+            params.remove(index);
+            Ok(Some(value))
+        }
+    }
+}
+
+// block id given by --block option, masterchain seqno is resolved to block id by client
+fn extract_block(params: &mut Vec<String>) -> Result<Option<BlockIdExt>> {
+    match extract_option(params, "--block")? {
+        Some(block) => Ok(Some(parse_blockid(Some(block), "block id")?)),
+        None => Ok(None)
+    }
+}
+
 fn parse_blockid<Q: ToString>(param_opt: Option<Q>, name: &str) -> Result<BlockIdExt> {
     parse_any(param_opt, name, |value| BlockIdExt::from_str(value))
 }
 
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |crc, byte| {
+        (0..8).fold(crc ^ ((*byte as u16) << 8), |crc, _| {
+            if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 }
+        })
+    })
+}
+
+// raw form wc:hex or user-friendly form (base64 or base64url, bounceable or not)
+fn parse_address(value: &str) -> Result<MsgAddressInt> {
+    if value.contains(':') {
+        return MsgAddressInt::from_str(value)
+    }
+    let data = base64::decode_config(value, base64::URL_SAFE)
+        .or_else(|_| base64::decode(value))
+        .map_err(|err| error!("address {} is neither raw nor base64: {}", value, err))?;
+    if data.len() != 36 {
+        fail!("user-friendly address {} must contain 36 bytes, but has {}", value, data.len())
+    }
+    if data[0] & 0x7F != 0x11 && data[0] & 0x7F != 0x51 {
+        fail!("user-friendly address {} has unknown flags {:#04x}", value, data[0])
+    }
+    if crc16(&data[..34]).to_be_bytes() != data[34..] {
+        fail!("user-friendly address {} has wrong checksum", value)
+    }
+    MsgAddressInt::with_standart(None, data[1] as i8, SliceData::from_raw(data[2..34].to_vec(), 256))
+}
+
+// elector accepts stakes only from std masterchain addresses and stores only account id
+fn parse_elector_wallet(value: &str) -> Result<Vec<u8>> {
+    const FORMS: &str = "use a masterchain address either in raw form -1:<64 hex digits> \
+        or in user-friendly form of 48 base64 or base64url symbols (bounceable or non-bounceable)";
+    let address = parse_address(value)
+        .map_err(|err| error!("{}, {}", err, FORMS))?;
+    match address {
+        MsgAddressInt::AddrStd(std) if std.anycast.is_none() => {
+            if std.workchain_id != -1 {
+                fail!(
+                    "elector accepts stakes only from masterchain, but address is in workchain {}, {}",
+                    std.workchain_id, FORMS
+                )
+            }
+            Ok(std.address.get_bytestring(0))
+        }
+        _ => fail!("elector does not accept stakes from anycast or var addresses, {}", FORMS)
+    }
+}
+
+// not every node serves lite server queries through control server,
+// the error of the node is explained in this case
+fn lite_server_error(err: failure::Error, query: &str) -> failure::Error {
+    match err.downcast::<ConsoleError>() {
+        Ok(ConsoleError::ControlQuery { query: control_query, code, message }) => ConsoleError::ControlQuery {
+            query: control_query,
+            code,
+            message: format!(
+                "{}, the node must serve lite_server.{} query in control server for --block option",
+                message, query
+            )
+        }.into(),
+        Ok(err) => err.into(),
+        Err(err) => err
+    }
+}
+
 fn now() -> ton::int {
     std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as ton::int
 }
@@ -170,6 +439,156 @@ fn stats_to_json<'a>(stats: impl IntoIterator<Item = &'a OneStat>) -> serde_json
     map.into()
 }
 
+/// Participation of one validator in consensus session
+#[derive(Default, serde::Serialize)]
+struct ValidatorParticipation {
+    id: String,
+    weight: Option<i64>,
+    signed: Option<i64>,
+    signing_rate: Option<f64>,
+    local: bool,
+    unknown: serde_json::Map<String, serde_json::Value>
+}
+
+/// Consensus session stats decoded from key-value list of the node by exact keys,
+/// keys which are not known or have unexpected values are kept in `unknown` as they are
+#[derive(Default, serde::Serialize)]
+struct SessionStats {
+    session_id: String,
+    catchain_seqno: Option<i64>,
+    shard: Option<String>,
+    // count of rounds, not index of the current one
+    rounds: Option<i64>,
+    approved: Option<i64>,
+    rejected: Option<i64>,
+    committed: Option<i64>,
+    validators: Vec<ValidatorParticipation>,
+    own_signing_rate: Option<f64>,
+    average_signing_rate: Option<f64>,
+    // own signing rate divided by session average
+    own_vs_average: Option<f64>,
+    unknown: serde_json::Map<String, serde_json::Value>
+}
+
+impl SessionStats {
+    // key aliases are matched as node versions name the stats differently,
+    // keys matched by no alias are reported in `unknown`
+    fn decode<'a>(session_id: &str, stats: impl IntoIterator<Item = &'a OneStat>) -> Self {
+        let mut result = Self {
+            session_id: session_id.to_string(),
+            ..Default::default()
+        };
+        let mut local_key = None;
+        let mut validators = vec![];
+        let mut unknown = vec![];
+        for stat in stats {
+            let value = stat.value.trim_matches('\"');
+            let decoded = match stat.key.as_str() {
+                "catchain_seqno" | "cc_seqno" => Self::parse_int(value, &mut result.catchain_seqno),
+                "shard" | "shard_id" | "shard_ident" => {
+                    result.shard = Some(value.to_string());
+                    true
+                }
+                // current_round is index of the round, not their count
+                "rounds" | "rounds_count" => Self::parse_int(value, &mut result.rounds),
+                "approved" | "approved_blocks" => Self::parse_int(value, &mut result.approved),
+                "rejected" | "rejected_blocks" => Self::parse_int(value, &mut result.rejected),
+                "committed" | "committed_blocks" => Self::parse_int(value, &mut result.committed),
+                "local_key" | "local_id" => {
+                    local_key = Some(value.to_string());
+                    true
+                }
+                "validators" | "participants" => match serde_json::from_str(&stat.value) {
+                    Ok(serde_json::Value::Array(list)) => {
+                        validators = list;
+                        true
+                    }
+                    // object keyed by validator id
+                    Ok(serde_json::Value::Object(map)) => {
+                        validators = map.into_iter().map(|(id, mut value)| {
+                            if let Some(validator) = value.as_object_mut() {
+                                validator.entry("id").or_insert(id.into());
+                            }
+                            value
+                        }).collect();
+                        true
+                    }
+                    _ => false
+                }
+                _ => false
+            };
+            if !decoded {
+                unknown.push(stat);
+            }
+        }
+        if let serde_json::Value::Object(unknown) = stats_to_json(unknown) {
+            result.unknown = unknown;
+        }
+        for validator in validators {
+            let mut validator = match validator {
+                serde_json::Value::Object(validator) => validator,
+                _ => continue
+            };
+            let id = Self::take(&mut validator, &["id", "public_key", "pub_key", "adnl_id", "key"], |value| {
+                value.as_str().map(|id| id.to_string())
+            }).unwrap_or_default();
+            let weight = Self::take_int(&mut validator, &["weight"]);
+            let signed = Self::take_int(&mut validator, &["signed", "signatures", "approved", "commits"]);
+            let local = Self::take(&mut validator, &["local", "is_local", "self"], |value| value.as_bool())
+                .unwrap_or_else(|| local_key.as_ref() == Some(&id));
+            result.validators.push(ValidatorParticipation {
+                signing_rate: match (signed, result.rounds) {
+                    (Some(signed), Some(rounds)) if rounds > 0 => Some(signed as f64 / rounds as f64),
+                    _ => None
+                },
+                id,
+                weight,
+                signed,
+                local,
+                unknown: validator
+            });
+        }
+        let rates = result.validators.iter().filter_map(|validator| validator.signing_rate).collect::<Vec<_>>();
+        if !rates.is_empty() {
+            result.average_signing_rate = Some(rates.iter().sum::<f64>() / rates.len() as f64);
+        }
+        result.own_signing_rate = result.validators.iter()
+            .find(|validator| validator.local)
+            .and_then(|validator| validator.signing_rate);
+        if let (Some(own), Some(average)) = (result.own_signing_rate, result.average_signing_rate) {
+            if average > 0.0 {
+                result.own_vs_average = Some(own / average);
+            }
+        }
+        result
+    }
+
+    fn parse_int(value: &str, field: &mut Option<i64>) -> bool {
+        *field = i64::from_str(value).ok();
+        field.is_some()
+    }
+
+    // removes the first alias with value of expected type, other values are left as unknown
+    fn take<T>(
+        map: &mut serde_json::Map<String, serde_json::Value>,
+        keys: &[&str],
+        decode: impl Fn(&serde_json::Value) -> Option<T>
+    ) -> Option<T> {
+        keys.iter().find_map(|key| {
+            let value = decode(map.get(*key)?)?;
+            map.remove(*key);
+            Some(value)
+        })
+    }
+
+    fn take_int(map: &mut serde_json::Map<String, serde_json::Value>, keys: &[&str]) -> Option<i64> {
+        Self::take(map, keys, |value| match value {
+            serde_json::Value::String(value) => i64::from_str(value).ok(),
+            value => value.as_i64()
+        })
+    }
+}
+
 impl SendReceive for GetStats {
     fn send<Q: ToString>(_params: impl Iterator<Item = Q>) -> Result<TLObject> {
         Ok(TLObject::new(ton::rpc::engine::validator::GetStats))
@@ -177,11 +596,10 @@ impl SendReceive for GetStats {
     fn receive<Q: ToString>(
         answer: TLObject, 
         mut _params: impl Iterator<Item = Q>
-    ) -> Result<(String, Vec<u8>)> {
+    ) -> Result<(serde_json::Value, Vec<u8>)> {
         let data = serialize_boxed(&answer)?;
         let stats = downcast::<ton_api::ton::engine::validator::Stats>(answer)?;
         let description = stats_to_json(stats.stats().iter());
-        let description = format!("{:#}", description);
         Ok((description, data))
     }
 }
@@ -196,11 +614,10 @@ impl SendReceive for GetSelectedStats {
     fn receive<Q: ToString>(
         answer: TLObject, 
         mut _params: impl Iterator<Item = Q>
-    ) -> Result<(String, Vec<u8>)> {
+    ) -> Result<(serde_json::Value, Vec<u8>)> {
         let data = serialize_boxed(&answer)?;
         let stats = downcast::<ton_api::ton::engine::validator::Stats>(answer)?;
         let description = stats_to_json(stats.stats().iter());
-        let description = format!("{:#}", description);
         Ok((description, data))
     }
 }
@@ -212,14 +629,14 @@ impl SendReceive for GetSessionStats {
     fn receive<Q: ToString>(
         answer: TLObject, 
         mut _params: impl Iterator<Item = Q>
-    ) -> Result<(String, Vec<u8>)> {
+    ) -> Result<(serde_json::Value, Vec<u8>)> {
         let data = serialize_boxed(&answer)?;
         let stats = downcast::<ton_api::ton::engine::validator::SessionStats>(answer)?;
         let description = stats.stats().iter().map(|session_stat| {
-            (session_stat.session_id.clone(), stats_to_json(session_stat.stats.iter()))
-        }).collect::<serde_json::Map<_, _>>();
-        let description = format!("{:#}", serde_json::Value::from(description));
-        Ok((description, data))
+            let stats = SessionStats::decode(&session_stat.session_id, session_stat.stats.iter());
+            Ok((session_stat.session_id.clone(), serde_json::to_value(stats)?))
+        }).collect::<Result<serde_json::Map<_, _>>>()?;
+        Ok((description.into(), data))
     }
 }
 
@@ -230,12 +647,14 @@ impl SendReceive for NewKeypair {
     fn receive<Q: ToString>(
         answer: TLObject, 
         mut _params: impl Iterator<Item = Q>
-    ) -> Result<(String, Vec<u8>)> {
+    ) -> Result<(serde_json::Value, Vec<u8>)> {
         let answer = downcast::<ton_api::ton::engine::validator::KeyHash>(answer)?;
         let key_hash = answer.key_hash().as_slice().to_vec();
-        Ok((format!("received public key hash: {} {}", 
-            hex::encode(&key_hash), base64::encode(&key_hash)), key_hash
-        ))
+        let description = serde_json::json!({
+            "key_hash": hex::encode(&key_hash),
+            "key_hash_base64": base64::encode(&key_hash),
+        });
+        Ok((description, key_hash))
     }
 }
 
@@ -249,21 +668,56 @@ impl SendReceive for ExportPub {
     fn receive<Q: ToString>(
         answer: TLObject, 
         mut _params: impl Iterator<Item = Q>
-    ) -> Result<(String, Vec<u8>)> {
+    ) -> Result<(serde_json::Value, Vec<u8>)> {
         let answer = downcast::<ton_api::ton::PublicKey>(answer)?;
         let pub_key = answer
             .key()
             .ok_or_else(|| error!("Public key not found in answer!"))?
             .as_slice()
             .to_vec();
-        Ok((format!("imported key: {} {}", hex::encode(&pub_key), base64::encode(&pub_key)), pub_key))
+        let description = serde_json::json!({
+            "public_key": hex::encode(&pub_key),
+            "public_key_base64": base64::encode(&pub_key),
+        });
+        Ok((description, pub_key))
+    }
+}
+
+/// Options of sign command, they are extracted both in send and receive
+struct SignOptions {
+    params: Vec<String>,
+    boc: bool,
+    format: Option<String>,
+    out: Option<String>
+}
+
+impl SignOptions {
+    fn extract<Q: ToString>(params: impl Iterator<Item = Q>) -> Result<Self> {
+        let mut params = params.map(|param| param.to_string()).collect::<Vec<_>>();
+        let boc = extract_flag(&mut params, &["--boc"]);
+        let format = extract_option(&mut params, "--format")?;
+        if let Some(format) = &format {
+            if format != "hex" && format != "base64" {
+                return Err(ConsoleError::Params(format!("unknown signature format {}, use hex or base64", format)).into())
+            }
+        }
+        let out = extract_option(&mut params, "--out")?;
+        Ok(Self { params, boc, format, out })
     }
 }
 
 impl SendReceive for Sign {
-    fn send<Q: ToString>(mut params: impl Iterator<Item = Q>) -> Result<TLObject> {
+    fn send<Q: ToString>(params: impl Iterator<Item = Q>) -> Result<TLObject> {
+        let options = SignOptions::extract(params)?;
+        let mut params = options.params.iter();
         let key_hash = parse_int256(params.next(), "key_hash")?;
-        let data = parse_data(params.next(), "data")?;
+        let mut data = parse_data(params.next(), "data")?;
+        if options.boc {
+            // representation hash of the root cell is signed
+            let cell = read_single_root_boc(&data.0)
+                .map_err(|err| ConsoleError::Params(format!("Can't read boc: {}", err)))?;
+            data = ton::bytes(cell.repr_hash().as_slice().to_vec());
+        }
         Ok(TLObject::new(ton::rpc::engine::validator::Sign {
             key_hash,
             data
@@ -271,11 +725,27 @@ impl SendReceive for Sign {
     }
     fn receive<Q: ToString>(
         answer: TLObject, 
-        mut _params: impl Iterator<Item = Q>
-    ) -> Result<(String, Vec<u8>)> {
+        params: impl Iterator<Item = Q>
+    ) -> Result<(serde_json::Value, Vec<u8>)> {
+        let options = SignOptions::extract(params)?;
         let answer = downcast::<ton_api::ton::engine::validator::Signature>(answer)?;
         let signature = answer.signature().0.clone();
-        Ok((format!("got signature: {} {}", hex::encode(&signature), base64::encode(&signature)), signature))
+        let mut description = match options.format.as_deref() {
+            Some("hex") => hex::encode(&signature).into(),
+            Some(_) => base64::encode(&signature).into(),
+            None => serde_json::json!({
+                "signature": hex::encode(&signature),
+                "signature_base64": base64::encode(&signature),
+            })
+        };
+        if let Some(path) = options.out {
+            std::fs::write(&path, &signature)
+                .map_err(|err| error!("Can't write signature to file {}: {}", path, err))?;
+            if description.is_object() {
+                description["path"] = path.into();
+            }
+        }
+        Ok((description, signature))
     }
 }
 
@@ -332,6 +802,78 @@ impl SendReceive for AddAdnlAddr {
     }
 }
 
+impl SendReceive for DelValidatorPermKey {
+    fn send<Q: ToString>(mut params: impl Iterator<Item = Q>) -> Result<TLObject> {
+        let key_hash = parse_int256(params.next(), "key_hash")?;
+        Ok(TLObject::new(ton::rpc::engine::validator::DelValidatorPermanentKey {
+            key_hash
+        }))
+    }
+}
+
+impl SendReceive for DelValidatorTempKey {
+    fn send<Q: ToString>(mut params: impl Iterator<Item = Q>) -> Result<TLObject> {
+        let permanent_key_hash = parse_int256(params.next(), "permanent_key_hash")?;
+        let key_hash = parse_int256(params.next(), "key_hash")?;
+        Ok(TLObject::new(ton::rpc::engine::validator::DelValidatorTempKey {
+            permanent_key_hash,
+            key_hash
+        }))
+    }
+}
+
+impl SendReceive for DelValidatorAdnlAddr {
+    fn send<Q: ToString>(mut params: impl Iterator<Item = Q>) -> Result<TLObject> {
+        let permanent_key_hash = parse_int256(params.next(), "permanent_key_hash")?;
+        let key_hash = parse_int256(params.next(), "key_hash")?;
+        Ok(TLObject::new(ton::rpc::engine::validator::DelValidatorAdnlAddress {
+            permanent_key_hash,
+            key_hash
+        }))
+    }
+}
+
+impl SendReceive for DelAdnlAddr {
+    fn send<Q: ToString>(mut params: impl Iterator<Item = Q>) -> Result<TLObject> {
+        let key_hash = parse_int256(params.next(), "key_hash")?;
+        Ok(TLObject::new(ton::rpc::engine::validator::DelAdnlId {
+            key_hash
+        }))
+    }
+}
+
+impl SendReceive for ImportKey {
+    fn send<Q: ToString>(mut params: impl Iterator<Item = Q>) -> Result<TLObject> {
+        let param = params.next().map(|param| param.to_string());
+        let key = match param.as_deref().and_then(|param| param.strip_prefix('@')) {
+            // keys file in TONOS-CLI format
+            Some(path) => {
+                let keys = std::fs::read_to_string(path)
+                    .map_err(|err| ConsoleError::Params(format!("Can't read keys file {}: {}", path, err)))?;
+                let keys = serde_json::from_str::<WalletKeysJson>(&keys)
+                    .map_err(|err| ConsoleError::Params(format!("Can't parse keys file {}: {}", path, err)))?;
+                parse_int256(Some(keys.secret), "secret")?
+            }
+            None => parse_int256(param, "private_key")?
+        };
+        Ok(TLObject::new(ton::rpc::engine::validator::ImportPrivateKey {
+            key: ton::PrivateKey::Pk_Ed25519(ton::pk::ed25519::Ed25519 { key })
+        }))
+    }
+    fn receive<Q: ToString>(
+        answer: TLObject,
+        mut _params: impl Iterator<Item = Q>
+    ) -> Result<(serde_json::Value, Vec<u8>)> {
+        let answer = downcast::<ton_api::ton::engine::validator::KeyHash>(answer)?;
+        let key_hash = answer.key_hash().as_slice().to_vec();
+        let description = serde_json::json!({
+            "key_hash": hex::encode(&key_hash),
+            "key_hash_base64": base64::encode(&key_hash),
+        });
+        Ok((description, key_hash))
+    }
+}
+
 impl SendReceive for Bundle {
     fn send<Q: ToString>(mut params: impl Iterator<Item = Q>) -> Result<TLObject> {
         let block_id = parse_blockid(params.next(), "block_id")?;
@@ -355,7 +897,7 @@ impl SendReceive for FutureBundle {
 
 impl SendReceive for SendMessage {
     fn send<Q: ToString>(mut params: impl Iterator<Item = Q>) -> Result<TLObject> {
-        let filename = params.next().ok_or_else(|| error!("insufficient parameters"))?.to_string();
+        let filename = params.next().ok_or_else(|| ConsoleError::Params("insufficient parameters".to_string()))?.to_string();
         let body = std::fs::read(&filename)
             .map_err(|e| error!("Can't read file {} with message: {}", filename, e))?;
         Ok(TLObject::new(ton::rpc::lite_server::SendMessage {body: body.into()}))
@@ -363,101 +905,328 @@ impl SendReceive for SendMessage {
 }
 
 impl SendReceive for GetBlockchainConfig {
-    fn send<Q: ToString>(_params: impl Iterator<Item = Q>) -> Result<TLObject> {
+    fn send<Q: ToString>(params: impl Iterator<Item = Q>) -> Result<TLObject> {
+        let mut params = params.map(|param| param.to_string()).collect::<Vec<_>>();
+        let proof_check = ProofCheck::extract(&mut params)?;
+        let block = extract_block(&mut params)?;
         Ok(TLObject::new(ton::rpc::lite_server::GetConfigAll {
             mode: 0,
-            id: BlockIdExt::default()
+            id: ProofCheck::target_block(&proof_check, block).unwrap_or_default()
         }))
     }
     fn receive<Q: ToString>(
         answer: TLObject, 
-        mut _params: impl Iterator<Item = Q>
-    ) -> Result<(String, Vec<u8>)> {
+        params: impl Iterator<Item = Q>
+    ) -> Result<(serde_json::Value, Vec<u8>)> {
+        let mut params = params.map(|param| param.to_string()).collect::<Vec<_>>();
+        let path = extract_option(&mut params, "--save")?;
+        let proof_check = ProofCheck::extract(&mut params)?;
+        let block = ProofCheck::target_block(&proof_check, extract_block(&mut params)?);
         let config_info = downcast::<ton_api::ton::lite_server::ConfigInfo>(answer)?;
 
         // We use config_proof because we use standard struct ConfigInfo from ton-tl and
         // ConfigInfo doesn`t contain more suitable fields
-        let config_param = hex::encode(config_info.config_proof().0.clone());
-        Ok((format!("{}", config_param), config_info.config_proof().0.clone()))
+        let data = config_info.config_proof().0.clone();
+        let config = match &proof_check {
+            Some(proof_check) => proof_check.check_config(&config_info, block.as_ref())?,
+            None => parse_config_proof(&data)?
+        };
+        let mut description = config_to_json(&config, None)?;
+        if let Some(proof_check) = &proof_check {
+            description["proof"] = proof_check.status().into();
+        }
+        save_config_proof(&mut description, path, &data)?;
+        Ok((description, data))
     }
 }
 
 impl SendReceive for GetConfig {
-    fn send<Q: ToString>(mut params: impl Iterator<Item = Q>) -> Result<TLObject> {
-        let param_number = parse_int(params.next(), "paramnumber")?;
+    fn send<Q: ToString>(params: impl Iterator<Item = Q>) -> Result<TLObject> {
+        let mut params = params.map(|param| param.to_string()).collect::<Vec<_>>();
+        extract_option(&mut params, "--save")?;
+        let proof_check = ProofCheck::extract(&mut params)?;
+        let id = ProofCheck::target_block(&proof_check, extract_block(&mut params)?).unwrap_or_default();
+        let param_number = parse_int(params.into_iter().next(), "paramnumber")?;
         let mut params: ton::vector<ton::Bare, ton::int> = ton::vector::default();
         params.0.push(param_number);
         Ok(TLObject::new(ton::rpc::lite_server::GetConfigParams {
             mode: 0,
-            id: BlockIdExt::default(),
+            id,
             param_list: params
         }))
     }
     fn receive<Q: ToString>(
         answer: TLObject, 
-        mut _params: impl Iterator<Item = Q>
-    ) -> Result<(String, Vec<u8>)> {
+        params: impl Iterator<Item = Q>
+    ) -> Result<(serde_json::Value, Vec<u8>)> {
+        let mut params = params.map(|param| param.to_string()).collect::<Vec<_>>();
+        let path = extract_option(&mut params, "--save")?;
+        let proof_check = ProofCheck::extract(&mut params)?;
+        let block = ProofCheck::target_block(&proof_check, extract_block(&mut params)?);
+        let param_number = parse_int(params.into_iter().next(), "paramnumber")?;
         let config_info = downcast::<ton_api::ton::lite_server::ConfigInfo>(answer)?;
-        let config_param = String::from_utf8(config_info.config_proof().0.clone())?;
-        Ok((config_param.to_string(), config_info.config_proof().0.clone()))
+        let data = config_info.config_proof().0.clone();
+        let mut description = match &proof_check {
+            Some(proof_check) => {
+                let config = proof_check.check_config(&config_info, block.as_ref())?;
+                let mut description = config_to_json(&config, Some(param_number as u32))?;
+                description["proof"] = proof_check.status().into();
+                description
+            }
+            None => match parse_config_proof(&data) {
+                Ok(config) => config_to_json(&config, Some(param_number as u32))?,
+                // old nodes answer with param already serialized to json
+                Err(err) => match String::from_utf8(data.clone()) {
+                    Ok(config_param) => serde_json::from_str(&config_param)
+                        .unwrap_or(serde_json::Value::String(config_param)),
+                    Err(_) => return Err(err)
+                }
+            }
+        };
+        save_config_proof(&mut description, path, &data)?;
+        Ok((description, data))
     }
 }
 
-impl SendReceive for GetAccount {
-    fn send<Q: ToString>(mut params: impl Iterator<Item = Q>) -> Result<TLObject> {
-        let account = AccountAddress { 
-            account_address: params.next().ok_or_else(|| error!("insufficient parameters"))?.to_string()
-        };
-        Ok(TLObject::new(ton::rpc::raw::GetShardAccountState {account_address: account}))
+fn config_param_cell(config: &ConfigParams, index: ton::int, source: &str) -> Result<Cell> {
+    let key = SliceData::load_builder(index.write_to_new_cell()?)?;
+    config.config_params.get(key)
+        .map_err(|err| error!("Can't read config param {} from {}: {}", index, source, err))?
+        .ok_or_else(|| error!("Can't find config param {} in {}", index, source))?
+        .reference_opt(0)
+        .ok_or_else(|| error!("Can't parse config param {}: wrong format - no reference", index))
+}
+
+// reads config params either from zerostate json or from json printed by getblockchainconfig
+fn read_config_json(path: &str) -> Result<ConfigParams> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|err| error!("Can't read config json file {} : {}", path, err))?;
+    let json = serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&json)
+        .map_err(|err| error!("Can't parse config json file {}: {}", path, err))?;
+    if !json.contains_key("master") {
+        return ton_block_json::parse_config(&json)
+            .map_err(|err| error!("Can't parse config json file {}: {}", path, err))
     }
+    let zerostate = ton_block_json::parse_state(&json)
+        .map_err(|err| error!("Can't parse zerostate json file {}: {}", path, err))?;
+    let extra = zerostate.read_custom()
+        .map_err(|err| error!("Can't read McStateExtra from zerostate: {}", err))?
+        .ok_or_else(|| error!("Can't find McStateExtra in zerostate"))?;
+    Ok(extra.config().clone())
+}
 
-    fn receive<Q: ToString>(
-        answer: TLObject, 
-        mut params: impl Iterator<Item = Q>
-    ) -> Result<(String, Vec<u8>)> {
-        let shard_account_state = downcast::<ShardAccountState>(answer)?;
-        let mut account_info = String::from("{");
-        account_info.push_str("\n\"");
-        account_info.push_str("acc_type\":\t\"");
+// collects changed leaves of two json values as {path, from, to}
+fn json_diff(path: &str, old: &serde_json::Value, new: &serde_json::Value, changes: &mut Vec<serde_json::Value>) {
+    match (old, new) {
+        (serde_json::Value::Object(old), serde_json::Value::Object(new)) => {
+            for (key, value) in old {
+                let path = format!("{}.{}", path, key);
+                match new.get(key) {
+                    Some(new) => json_diff(&path, value, new, changes),
+                    None => changes.push(serde_json::json!({"path": path, "from": value, "to": null}))
+                }
+            }
+            for (key, value) in new {
+                if !old.contains_key(key) {
+                    let path = format!("{}.{}", path, key);
+                    changes.push(serde_json::json!({"path": path, "from": null, "to": value}))
+                }
+            }
+        }
+        (serde_json::Value::Array(old), serde_json::Value::Array(new)) => {
+            for index in 0..old.len().max(new.len()) {
+                let path = format!("{}[{}]", path, index);
+                match (old.get(index), new.get(index)) {
+                    (Some(old), Some(new)) => json_diff(&path, old, new, changes),
+                    (old, new) => changes.push(serde_json::json!({"path": path, "from": old, "to": new}))
+                }
+            }
+        }
+        (old, new) => if old != new {
+            changes.push(serde_json::json!({"path": path, "from": old, "to": new}))
+        }
+    }
+}
 
-        match shard_account_state {
-            ShardAccountState::Raw_ShardAccountNone => {
-                account_info.push_str(&"Nonexist");
-            },
-            ShardAccountState::Raw_ShardAccountState(account_state) => {
-                let shard_account = ShardAccount::construct_from_bytes(&account_state.shard_account)?;
-                let account = shard_account.read_account()?;
+fn config_address(config: &ConfigParams) -> Result<MsgAddressInt> {
+    MsgAddressInt::from_str(&format!("-1:{}", config.config_addr.to_hex_string()))
+}
 
-                let account_type = match account.status() {
-                    AccountStatus::AccStateUninit => "Uninit",
-                    AccountStatus::AccStateFrozen => "Frozen",
-                    AccountStatus::AccStateActive => "Active",
-                    AccountStatus::AccStateNonexist => "Nonexist"
+// renders all or one config param as structured json through ton_block_json
+fn config_to_json(config: &ConfigParams, param_number: Option<u32>) -> Result<serde_json::Value> {
+    let mut numbers = vec![];
+    match param_number {
+        Some(number) => numbers.push(number),
+        None => {
+            config.config_params.iterate_slices(|mut key, _| {
+                numbers.push(key.get_next_u32()?);
+                Ok(true)
+            })?;
+        }
+    }
+    let mut description = serde_json::Map::new();
+    for number in numbers {
+        let param = ton_block_json::serialize_config_param(config, number)
+            .map_err(|err| error!("Can't serialize config param {}: {}", number, err))?;
+        match serde_json::from_str::<serde_json::Value>(&param)? {
+            serde_json::Value::Object(param) => description.extend(param),
+            param => {
+                description.insert(format!("p{}", number), param);
+            }
+        }
+    }
+    Ok(description.into())
+}
+
+fn save_config_proof(description: &mut serde_json::Value, path: Option<String>, data: &[u8]) -> Result<()> {
+    if let Some(path) = path {
+        std::fs::write(&path, data)
+            .map_err(|err| error!("Can't write config boc to file {}: {}", path, err))?;
+        description["boc_path"] = path.into();
+    }
+    Ok(())
+}
+
+// last transaction hash is known only for accounts from the last state
+fn account_to_json(
+    account: Account,
+    last_trans_hash: Option<&UInt256>,
+    with_boc: bool,
+    decode: bool
+) -> Result<serde_json::Value> {
+    let account_type = match account.status() {
+        AccountStatus::AccStateUninit => "Uninit",
+        AccountStatus::AccStateFrozen => "Frozen",
+        AccountStatus::AccStateActive => "Active",
+        AccountStatus::AccStateNonexist => "Nonexist"
+    };
+    let mut account_info = serde_json::json!({
+        "acc_type": account_type,
+        "address": account.get_addr().map(|addr| addr.to_string()),
+        "last_trans_lt": account.last_tr_time(),
+        "last_trans_hash": last_trans_hash.map(|hash| hash.to_hex_string()),
+    });
+    if let Some(balance) = account.balance() {
+        let mut other = serde_json::Map::new();
+        balance.other.iterate_with_keys(|key: u32, value| {
+            other.insert(key.to_string(), value.value().to_string().into());
+            Ok(true)
+        })?;
+        account_info["balance"] = balance.grams.as_u128().to_string().into();
+        account_info["balance_other"] = other.into();
+    }
+    if let Some(storage) = account.storage_info() {
+        account_info["storage_stat"] = serde_json::json!({
+            "used_cells": storage.used().cells(),
+            "used_bits": storage.used().bits(),
+            "used_public_cells": storage.used().public_cells(),
+            "last_paid": storage.last_paid(),
+            "due_payment": storage.due_payment().map(|due| due.as_u128().to_string()),
+        });
+    }
+    if let Some(code) = account.get_code() {
+        account_info["code_hash"] = code.repr_hash().to_hex_string().into();
+    }
+    if let Some(data) = account.get_data() {
+        account_info["data_hash"] = data.repr_hash().to_hex_string().into();
+    }
+    let boc = write_boc(&account.serialize()?)?;
+    if decode {
+        let set = ton_block_json::AccountSerializationSet {
+            account,
+            prev_code_hash: None,
+            boc: boc.clone(),
+            proof: None
+        };
+        let decoded = ton_block_json::db_serialize_account_ex(
+            "id", &set, ton_block_json::SerializationMode::Debug
+        )?;
+        account_info["decoded"] = decoded.into();
+    }
+    if with_boc {
+        account_info["boc"] = base64::encode(&boc).into();
+    }
+    Ok(account_info)
+}
+
+impl SendReceive for GetAccount {
+    fn send<Q: ToString>(params: impl Iterator<Item = Q>) -> Result<TLObject> {
+        let mut params = params.map(|param| param.to_string()).collect::<Vec<_>>();
+        extract_flag(&mut params, &["--boc", "--decode"]);
+        let proof_check = ProofCheck::extract(&mut params)?;
+        let block = ProofCheck::target_block(&proof_check, extract_block(&mut params)?);
+        if proof_check.is_some() && block.is_none() {
+            return Err(ConsoleError::Params(
+                "account from the last state has no proof, use --block to get account with proof".to_string()
+            ).into())
+        }
+        let account_address = params.into_iter().next().ok_or_else(|| ConsoleError::Params("insufficient parameters".to_string()))?;
+        match block {
+            Some(id) => {
+                let address = parse_address(&account_address)?;
+                let account = ton::lite_server::accountid::AccountId {
+                    workchain: address.workchain_id(),
+                    id: UInt256::from_slice(&address.address().get_bytestring(0))
                 };
-                let balance = account.balance().map_or(0, |val| val.grams.as_u128());
-                account_info.push_str(&account_type);
-                account_info.push_str("\",\n\"");
-                account_info.push_str("balance\":\t");
-                account_info.push_str(&balance.to_string());
-                account_info.push_str(",\n\"");
-                account_info.push_str("last_paid\":\t");
-                account_info.push_str(&account.last_paid().to_string());
-                account_info.push_str(",\n\"");
-                account_info.push_str("last_trans_lt\":\t\"");
-                account_info.push_str(&format!("{:#x}", shard_account.last_trans_lt()));
-                account_info.push_str("\",\n\"");
-                account_info.push_str("data(boc)\":\t\"");
-                account_info.push_str(
-                    &hex::encode(&write_boc(&shard_account.account_cell())?)
-                );
-            }
-        }
-        account_info.push_str("\"\n}");
+                Ok(TLObject::new(ton::rpc::lite_server::GetAccountState {id, account}))
+            }
+            None => {
+                let account = AccountAddress { account_address };
+                Ok(TLObject::new(ton::rpc::raw::GetShardAccountState {account_address: account}))
+            }
+        }
+    }
+
+    fn receive<Q: ToString>(
+        answer: TLObject, 
+        params: impl Iterator<Item = Q>
+    ) -> Result<(serde_json::Value, Vec<u8>)> {
+        let mut params = params.map(|param| param.to_string()).collect::<Vec<_>>();
+        let with_boc = extract_flag(&mut params, &["--boc"]);
+        let decode = extract_flag(&mut params, &["--decode"]);
+        let proof_check = ProofCheck::extract(&mut params)?;
+        let block = ProofCheck::target_block(&proof_check, extract_block(&mut params)?);
+        let mut params = params.into_iter();
+        let nonexist = serde_json::json!({
+            "acc_type": "Nonexist"
+        });
+        let mut account_info = match &block {
+            Some(block) => {
+                let account_state = downcast::<ton::lite_server::AccountState>(answer)?;
+                if let Some(proof_check) = &proof_check {
+                    let address = parse_any(params.clone().next(), "account address", parse_address)?;
+                    proof_check.check_account(&account_state, block, &address)?;
+                }
+                if account_state.state().0.is_empty() {
+                    nonexist
+                } else {
+                    let account = Account::construct_from_bytes(&account_state.state().0)?;
+                    account_to_json(account, None, with_boc, decode)?
+                }
+            }
+            None if proof_check.is_some() => return Err(proof_invalid(
+                "account from the last state has no proof, use --block to get account with proof".to_string()
+            )),
+            None => match downcast::<ShardAccountState>(answer)? {
+                ShardAccountState::Raw_ShardAccountNone => nonexist,
+                ShardAccountState::Raw_ShardAccountState(account_state) => {
+                    let shard_account = ShardAccount::construct_from_bytes(&account_state.shard_account)?;
+                    let hash = shard_account.last_trans_hash().clone();
+                    account_to_json(shard_account.read_account()?, Some(&hash), with_boc, decode)?
+                }
+            }
+        };
+        if let Some(block) = block {
+            account_info["block"] = block.to_string().into();
+        }
+        if let Some(proof_check) = &proof_check {
+            account_info["proof"] = proof_check.status().into();
+        }
 
         params.next();
-        let account_data = account_info.as_bytes().to_vec();
+        let account_data = format!("{:#}", account_info).into_bytes();
         if let Some(boc_name) = params.next() {
-            std::fs::write(boc_name.to_string(), &account_data)
+            std::fs::write(boc_name, &account_data)
                 .map_err(|err| error!("Can`t create file: {}", err))?;
         }
 
@@ -467,7 +1236,7 @@ impl SendReceive for GetAccount {
 
 impl SendReceive for GetAccountState {
     fn send<Q: ToString>(mut params: impl Iterator<Item = Q>) -> Result<TLObject> {
-        let account_address = params.next().ok_or_else(|| error!("insufficient parameters"))?.to_string();
+        let account_address = params.next().ok_or_else(|| ConsoleError::Params("insufficient parameters".to_string()))?.to_string();
         let account_address = AccountAddress { account_address };
         Ok(TLObject::new(ton::rpc::raw::GetShardAccountState {account_address}))
     }
@@ -475,7 +1244,7 @@ impl SendReceive for GetAccountState {
     fn receive<Q: ToString>(
         answer: TLObject, 
         mut params: impl Iterator<Item = Q>
-    ) -> Result<(String, Vec<u8>)> {
+    ) -> Result<(serde_json::Value, Vec<u8>)> {
         let shard_account_state = downcast::<ShardAccountState>(answer)?;
 
         params.next();
@@ -493,68 +1262,806 @@ impl SendReceive for GetAccountState {
         std::fs::write(boc_name, &account_state)
             .map_err(|err| error!("Can`t create file: {}", err))?;
 
-        Ok((format!("{} {}",
-            hex::encode(&account_state),
-            base64::encode(&account_state)),
-            account_state)
-        )
+        let description = serde_json::json!({
+            "boc": hex::encode(&account_state),
+            "boc_base64": base64::encode(&account_state),
+        });
+        Ok((description, account_state))
     }
 }
 
 impl SendReceive for SetStatesGcInterval {
     fn send<Q: ToString>(mut params: impl Iterator<Item = Q>) -> Result<TLObject> {
-        let interval_ms_str = params.next().ok_or_else(|| error!("insufficient parameters"))?.to_string();
+        let interval_ms_str = params.next().ok_or_else(|| ConsoleError::Params("insufficient parameters".to_string()))?.to_string();
         let interval_ms = interval_ms_str.parse().map_err(|e| error!("can't parse <milliseconds>: {}", e))?;
         Ok(TLObject::new(ton::rpc::engine::validator::SetStatesGcInterval { interval_ms }))
     }
 }
 
-/// ControlClient
-struct ControlClient{
-    config: AdnlConsoleConfigJson,
-    adnl: AdnlClient,
+/// Options of wallet external message carrying elector message body
+struct WalletTransfer {
+    value: u64,
+    path: String,
+    send: bool
 }
 
-impl ControlClient {
+impl WalletTransfer {
 
-    /// Connect to server
-    async fn connect(mut config: AdnlConsoleConfigJson) -> Result<Self> {
-        let client_config = config.config.take()
-            .ok_or_else(|| error!("config must contain \"config\" section"))?;
-        let (_, adnl_config) = AdnlClientConfig::from_json_config(client_config)?;
-        Ok(Self {
-            config,
-            adnl: AdnlClient::connect(&adnl_config).await?,
-        })
-    }
+    // 1 token is enough to pay for recover stake processing, the rest is returned
+    const RECOVER_VALUE: u64 = 1_000_000_000;
 
-    /// Shutdown client
-    async fn shutdown(self) -> Result<()> {
-        self.adnl.shutdown().await
+    // wallet message is built only if any of its options is given
+    fn extract(
+        params: &mut Vec<String>,
+        value_option: &str,
+        default_value: Option<u64>,
+        default_path: &str
+    ) -> Result<Option<Self>> {
+        let send = extract_flag(params, &["--send"]);
+        let path = extract_option(params, "--msg-file")?;
+        let value = match extract_option(params, value_option)? {
+            Some(value) => u64::from_str(&value)
+                .map_err(|err| error!("you must give {} in nanotokens: {}", value_option, err))?,
+            None if !send && path.is_none() => return Ok(None),
+            None => default_value
+                .ok_or_else(|| error!("{} must be given to build wallet message", value_option))?
+        };
+        Ok(Some(Self {
+            value,
+            path: path.unwrap_or_else(|| default_path.to_string()),
+            send
+        }))
     }
+}
 
-    async fn command(&mut self, cmd: &str) -> Result<(String, Vec<u8>)> {
-        let result = shell_words::split(cmd)?;
-        let mut params = result.iter();
-        match params.next().expect("takes_value set for COMMANDS").as_str() {
-            "recover_stake" => self.process_recover_stake(params).await,
-            "ebid" |
-            "election-bid" |
-            "election_bid" => self.process_election_bid(params).await,
-            "config_param" |
-            "cparam" => self.process_config_param(params).await,
-            name => self.process_command(name, params).await
-        }
+// SafeMultisig/SetcodeMultisig submitTransaction(address,uint128,bool,bool,cell)(uint64) function id
+const SUBMIT_TRANSACTION_ID: u32 = 0x131D82CD;
+const ELECTOR_ADDRESS: &str = "-1:3333333333333333333333333333333333333333333333333333333333333333";
+const WALLET_MESSAGE_TTL: u32 = 60;
+
+// external call of submitTransaction in ABI 2.0 layout without signature:
+// pubkey, time and expire headers, function id and parameters in next cell
+fn submit_transaction_call(pub_key: &[u8], dest: &MsgAddressInt, value: u64, payload: Cell) -> Result<BuilderData> {
+    let mut params = BuilderData::new();
+    dest.write_to(&mut params)?;
+    params.append_raw(&(value as u128).to_be_bytes(), 128)?;
+    params.append_bit_one()?; // bounce
+    params.append_bit_zero()?; // allBalance
+    params.checked_append_reference(payload)?;
+    let time = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64;
+    let expire = now() as u32 + WALLET_MESSAGE_TTL;
+    let mut call = BuilderData::new();
+    call.append_bit_one()?;
+    call.append_raw(pub_key, 256)?;
+    call.append_raw(&time.to_be_bytes(), 64)?;
+    call.append_raw(&expire.to_be_bytes(), 32)?;
+    call.append_raw(&SUBMIT_TRANSACTION_ID.to_be_bytes(), 32)?;
+    call.checked_append_reference(params.into_cell()?)?;
+    Ok(call)
+}
+
+/// Parameters of validator election request
+struct ElectionBid {
+    elect_time: ton::int,
+    expire_time: ton::int,
+    max_factor: u32,
+    wallet_id: Vec<u8>,
+    perm_key: Option<UInt256>,
+    adnl_key: Option<UInt256>
+}
+
+impl ElectionBid {
+
+    const PERM_KEY: &'static str = "<perm_key_hash>";
+    const ADNL_KEY: &'static str = "<adnl_key_hash>";
+
+    // validator-elect-req.fif
+    fn request(&self, adnl: &[u8]) -> Vec<u8> {
+        let mut data = 0x654C5074u32.to_be_bytes().to_vec();
+        data.extend_from_slice(&self.elect_time.to_be_bytes());
+        data.extend_from_slice(&self.max_factor.to_be_bytes());
+        data.extend_from_slice(&self.wallet_id);
+        data.extend_from_slice(adnl);
+        data
     }
 
-    async fn process_command<Q: ToString>(
-        &mut self,
+    // validator-elect-signed.fif
+    fn signed_body(&self, pub_key: &[u8], adnl: &[u8], signature: Vec<u8>) -> Result<Cell> {
+        let query_id = now() as u64;
+        let mut data = 0x4E73744Bu32.to_be_bytes().to_vec();
+        data.extend_from_slice(&query_id.to_be_bytes());
+        data.extend_from_slice(pub_key);
+        data.extend_from_slice(&self.elect_time.to_be_bytes());
+        data.extend_from_slice(&self.max_factor.to_be_bytes());
+        data.extend_from_slice(adnl);
+        let len = data.len() * 8;
+        let mut body = BuilderData::with_raw(data, len)?;
+        let len = signature.len() * 8;
+        body.checked_append_reference(BuilderData::with_raw(signature, len)?.into_cell()?)?;
+        body.into_cell()
+    }
+
+    /// Describes control queries and unsigned payload without sending anything to the node
+    fn plan(&self, path: &str, transfer: Option<&WalletTransfer>) -> Result<(serde_json::Value, Vec<u8>)> {
+        let elect_time = self.elect_time.to_string();
+        let expire_time = self.expire_time.to_string();
+        let perm = self.perm_key.as_ref()
+            .map_or_else(|| Self::PERM_KEY.to_string(), |key| hex::encode_upper(key.as_slice()));
+        let adnl = self.adnl_key.as_ref()
+            .map_or_else(|| Self::ADNL_KEY.to_string(), |key| hex::encode_upper(key.as_slice()));
+        let mut queries = vec![];
+        if self.perm_key.is_none() {
+            queries.push("newkey".to_string());
+        }
+        queries.push(format!("exportpub {}", perm));
+        if self.perm_key.is_none() {
+            queries.push(format!("addpermkey {} {} {}", perm, elect_time, expire_time));
+            queries.push(format!("addtempkey {} {} {}", perm, perm, expire_time));
+        }
+        if self.adnl_key.is_none() {
+            queries.push("newkey".to_string());
+            queries.push(format!("addadnl {} 0", adnl));
+        }
+        // given ADNL address must be bound to new perm key too
+        if self.adnl_key.is_none() || self.perm_key.is_none() {
+            queries.push(format!("addvalidatoraddr {} {} {}", perm, adnl, elect_time));
+        }
+        queries.push(format!("sign {} <payload>", perm));
+        if let Some(transfer) = transfer.filter(|transfer| transfer.send) {
+            queries.push(format!("sendmessage {}", transfer.path));
+        }
+        // ADNL address is unknown until it is generated by the node
+        let data = match &self.adnl_key {
+            Some(key) => self.request(key.as_slice()),
+            None => self.request(&[])
+        };
+        let hex = match &self.adnl_key {
+            Some(_) => hex::encode_upper(&data),
+            None => format!("{}{}", hex::encode_upper(&data), Self::ADNL_KEY)
+        };
+        let plan = serde_json::json!({
+            "dry_run": true,
+            "queries": queries,
+            "payload": {
+                "tag": "654C5074",
+                "elect_time": self.elect_time,
+                "max_factor": self.max_factor,
+                "wallet_id": hex::encode_upper(&self.wallet_id),
+                "adnl_addr": adnl,
+                "hex": hex,
+            },
+            "output": path,
+            "wallet_message": transfer.map(|transfer| serde_json::json!({
+                "dest": ELECTOR_ADDRESS,
+                "value": transfer.value,
+                "output": transfer.path,
+            })),
+        });
+        Ok((plan, data))
+    }
+}
+
+/// Stake of participant of elections in progress
+#[derive(Default)]
+struct Participant {
+    pub_key: UInt256,
+    stake: u128,
+    time: u32,
+    max_factor: u32,
+    wallet: UInt256,
+    adnl_addr: UInt256
+}
+
+impl Deserializable for Participant {
+    fn read_from(&mut self, slice: &mut SliceData) -> Result<()> {
+        self.stake = Grams::construct_from(slice)?.as_u128();
+        self.time = slice.get_next_u32()?;
+        self.max_factor = slice.get_next_u32()?;
+        self.wallet = slice.get_next_hash()?;
+        self.adnl_addr = slice.get_next_hash()?;
+        Ok(())
+    }
+}
+
+/// Elections in progress
+#[derive(Default)]
+struct Elections {
+    elect_at: u32,
+    elect_close: u32,
+    min_stake: u128,
+    total_stake: u128,
+    participants: Vec<Participant>,
+    failed: bool,
+    finished: bool
+}
+
+impl Deserializable for Elections {
+    // elector-code.fc: unpack_elect()
+    fn read_from(&mut self, slice: &mut SliceData) -> Result<()> {
+        self.elect_at = slice.get_next_u32()?;
+        self.elect_close = slice.get_next_u32()?;
+        self.min_stake = Grams::construct_from(slice)?.as_u128();
+        self.total_stake = Grams::construct_from(slice)?.as_u128();
+        self.participants.clear();
+        iterate_dictionary(read_dictionary(slice)?, 256, |mut key, mut value| {
+            let mut participant = Participant::construct_from(&mut value)?;
+            participant.pub_key = key.get_next_hash()?;
+            self.participants.push(participant);
+            Ok(())
+        })?;
+        self.failed = slice.get_next_bit()?;
+        self.finished = slice.get_next_bit()?;
+        Ok(())
+    }
+}
+
+impl Elections {
+    // elector-code.fc: try_elect(), returns the least stake among elected validators
+    fn min_elected_stake(&self, config: &ConfigParams) -> Result<Option<u128>> {
+        let (min_validators, max_validators) = match config.config(16)? {
+            Some(ConfigParamEnum::ConfigParam16(param)) => {
+                (param.min_validators.as_u16() as usize, param.max_validators.as_u16() as usize)
+            }
+            _ => fail!("config param 16 not found")
+        };
+        let (max_stake, max_stake_factor) = match config.config(17)? {
+            Some(ConfigParamEnum::ConfigParam17(param)) => (param.max_stake.as_u128(), param.max_stake_factor),
+            _ => fail!("config param 17 not found")
+        };
+        // elector caps max factor of every participant by max_stake_factor of config param 17
+        let mut stakes = self.participants.iter()
+            .map(|participant| (
+                participant.stake.min(max_stake),
+                participant.max_factor.min(max_stake_factor),
+                participant.time
+            ))
+            .collect::<Vec<_>>();
+        stakes.sort_by(|a, b| b.0.cmp(&a.0).then(a.2.cmp(&b.2)));
+        stakes.truncate(max_validators);
+        let mut best = None;
+        let mut best_total = 0;
+        for count in min_validators.max(1)..=stakes.len() {
+            let min_stake = stakes[count - 1].0;
+            let total = stakes[..count].iter()
+                .map(|(stake, max_factor, _)| (*stake).min((min_stake * *max_factor as u128) >> 16))
+                .sum::<u128>();
+            if total > best_total {
+                best_total = total;
+                best = Some(min_stake);
+            }
+        }
+        Ok(best)
+    }
+}
+
+/// Frozen stake of validator from past elections
+#[derive(Default)]
+struct FrozenStake {
+    pub_key: UInt256,
+    wallet: UInt256,
+    weight: u64,
+    stake: u128,
+    banned: bool
+}
+
+impl Deserializable for FrozenStake {
+    fn read_from(&mut self, slice: &mut SliceData) -> Result<()> {
+        self.wallet = slice.get_next_hash()?;
+        self.weight = slice.get_next_u64()?;
+        self.stake = Grams::construct_from(slice)?.as_u128();
+        self.banned = slice.get_next_bit()?;
+        Ok(())
+    }
+}
+
+/// Finished elections whose stakes are still frozen
+#[derive(Default)]
+struct PastElections {
+    elect_id: u32,
+    unlocked_at: u32,
+    stake_held: u32,
+    vset_hash: UInt256,
+    frozen: Vec<FrozenStake>,
+    total_stake: u128,
+    bonuses: u128
+}
+
+impl Deserializable for PastElections {
+    // elector-code.fc: unpack_past_election()
+    fn read_from(&mut self, slice: &mut SliceData) -> Result<()> {
+        self.unlocked_at = slice.get_next_u32()?;
+        self.stake_held = slice.get_next_u32()?;
+        self.vset_hash = slice.get_next_hash()?;
+        self.frozen.clear();
+        iterate_dictionary(read_dictionary(slice)?, 256, |mut key, mut value| {
+            let mut frozen = FrozenStake::construct_from(&mut value)?;
+            frozen.pub_key = key.get_next_hash()?;
+            self.frozen.push(frozen);
+            Ok(())
+        })?;
+        self.total_stake = Grams::construct_from(slice)?.as_u128();
+        self.bonuses = Grams::construct_from(slice)?.as_u128();
+        Ok(())
+    }
+}
+
+/// Elector contract persistent data
+#[derive(Default)]
+struct ElectorData {
+    current: Option<Elections>,
+    credits: Vec<(UInt256, u128)>,
+    past_elections: Vec<PastElections>,
+    grams: u128,
+    active_id: u32,
+    active_hash: UInt256
+}
+
+impl Deserializable for ElectorData {
+    // elector-code.fc: load_data()
+    fn read_from(&mut self, slice: &mut SliceData) -> Result<()> {
+        self.current = match read_dictionary(slice)? {
+            Some(cell) => Some(Elections::construct_from_cell(cell)?),
+            None => None
+        };
+        self.credits.clear();
+        iterate_dictionary(read_dictionary(slice)?, 256, |mut key, mut value| {
+            self.credits.push((key.get_next_hash()?, Grams::construct_from(&mut value)?.as_u128()));
+            Ok(())
+        })?;
+        self.past_elections.clear();
+        iterate_dictionary(read_dictionary(slice)?, 32, |mut key, mut value| {
+            let mut past_elections = PastElections::construct_from(&mut value)?;
+            past_elections.elect_id = key.get_next_u32()?;
+            self.past_elections.push(past_elections);
+            Ok(())
+        })?;
+        self.grams = Grams::construct_from(slice)?.as_u128();
+        self.active_id = slice.get_next_u32()?;
+        self.active_hash = slice.get_next_hash()?;
+        Ok(())
+    }
+}
+
+impl ElectorData {
+    fn credit(&self, wallet: &UInt256) -> u128 {
+        self.credits.iter()
+            .find(|(address, _)| address == wallet)
+            .map_or(0, |(_, credit)| *credit)
+    }
+}
+
+// iterates over HashmapE with given key length
+fn iterate_dictionary(
+    root: Option<Cell>,
+    bit_len: usize,
+    mut func: impl FnMut(SliceData, SliceData) -> Result<()>
+) -> Result<()> {
+    HashmapE::with_hashmap(bit_len, root).iterate_slices(|key, value| {
+        func(key, value)?;
+        Ok(true)
+    })?;
+    Ok(())
+}
+
+/// Proposal to change config param
+#[derive(Default)]
+struct ConfigProposal {
+    param_id: i32,
+    param_value: Option<Cell>,
+    if_hash_equal: Option<UInt256>
+}
+
+impl ConfigProposal {
+    const TAG: u8 = 0xf3;
+}
+
+impl Deserializable for ConfigProposal {
+    // cfg_proposal#f3 param_id:int32 param_value:(Maybe ^Cell) if_hash_equal:(Maybe uint256)
+    fn read_from(&mut self, slice: &mut SliceData) -> Result<()> {
+        let tag = slice.get_next_byte()?;
+        if tag != Self::TAG {
+            fail!("wrong config proposal tag {:x}", tag)
+        }
+        self.param_id = slice.get_next_i32()?;
+        self.param_value = read_dictionary(slice)?;
+        self.if_hash_equal = match slice.get_next_bit()? {
+            true => Some(slice.get_next_hash()?),
+            false => None
+        };
+        Ok(())
+    }
+}
+
+impl Serializable for ConfigProposal {
+    fn write_to(&self, cell: &mut BuilderData) -> Result<()> {
+        cell.append_u8(Self::TAG)?;
+        cell.append_i32(self.param_id)?;
+        match &self.param_value {
+            Some(value) => {
+                cell.append_bit_one()?;
+                cell.checked_append_reference(value.clone())?;
+            }
+            None => {
+                cell.append_bit_zero()?;
+            }
+        }
+        match &self.if_hash_equal {
+            Some(hash) => {
+                cell.append_bit_one()?;
+                cell.append_raw(hash.as_slice(), 256)?;
+            }
+            None => {
+                cell.append_bit_zero()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Voting for config proposal
+#[derive(Default)]
+struct ConfigProposalStatus {
+    hash: UInt256,
+    expires: u32,
+    proposal: ConfigProposal,
+    critical: bool,
+    voters: Vec<u16>,
+    remaining_weight: i64,
+    validator_set_id: UInt256,
+    rounds_remaining: u8,
+    wins: u8,
+    losses: u8
+}
+
+impl Deserializable for ConfigProposalStatus {
+    // cfg_proposal_status#ce expires:uint32 proposal:^ConfigProposal is_critical:Bool
+    // voters:(HashmapE 16 True) remaining_weight:int64 validator_set_id:uint256
+    // rounds_remaining:uint8 wins:uint8 losses:uint8
+    fn read_from(&mut self, slice: &mut SliceData) -> Result<()> {
+        let tag = slice.get_next_byte()?;
+        if tag != 0xce {
+            fail!("wrong config proposal status tag {:x}", tag)
+        }
+        self.expires = slice.get_next_u32()?;
+        self.proposal = ConfigProposal::construct_from_cell(slice.checked_drain_reference()?)?;
+        self.critical = slice.get_next_bit()?;
+        self.voters.clear();
+        iterate_dictionary(read_dictionary(slice)?, 16, |mut key, _| {
+            self.voters.push(key.get_next_u16()?);
+            Ok(())
+        })?;
+        self.remaining_weight = slice.get_next_i64()?;
+        self.validator_set_id = slice.get_next_hash()?;
+        self.rounds_remaining = slice.get_next_byte()?;
+        self.wins = slice.get_next_byte()?;
+        self.losses = slice.get_next_byte()?;
+        Ok(())
+    }
+}
+
+/// Config contract persistent data
+#[derive(Default)]
+struct ConfigContractData {
+    seqno: u32,
+    public_key: UInt256,
+    proposals: Vec<ConfigProposalStatus>
+}
+
+impl Deserializable for ConfigContractData {
+    // config-code.fc: load_data()
+    fn read_from(&mut self, slice: &mut SliceData) -> Result<()> {
+        slice.checked_drain_reference()?;
+        self.seqno = slice.get_next_u32()?;
+        self.public_key = slice.get_next_hash()?;
+        self.proposals.clear();
+        iterate_dictionary(read_dictionary(slice)?, 256, |mut key, mut value| {
+            let mut status = ConfigProposalStatus::construct_from(&mut value)?;
+            status.hash = key.get_next_hash()?;
+            self.proposals.push(status);
+            Ok(())
+        })?;
+        Ok(())
+    }
+}
+
+// reads root of HashmapE or Maybe ^Cell
+fn read_dictionary(slice: &mut SliceData) -> Result<Option<Cell>> {
+    match slice.get_next_bit()? {
+        true => Ok(Some(slice.checked_drain_reference()?)),
+        false => Ok(None)
+    }
+}
+
+// config proof is either serialized config params or Merkle proof of masterchain state
+fn parse_config_proof(data: &[u8]) -> Result<ConfigParams> {
+    let root = read_single_root_boc(data)?;
+    if root.cell_type() != CellType::MerkleProof {
+        return ConfigParams::construct_from_cell(root)
+    }
+    let proof = MerkleProof::construct_from_cell(root)?;
+    let state = ShardStateUnsplit::construct_from_cell(proof.proof.virtualize(1))?;
+    let extra = state.read_custom()?
+        .ok_or_else(|| error!("config proof does not contain McStateExtra"))?;
+    Ok(extra.config)
+}
+
+fn proof_invalid(message: String) -> failure::Error {
+    ConsoleError::ProofInvalid(message).into()
+}
+
+// checks Merkle proof against expected hash and returns virtualized root of proven tree
+fn check_merkle_proof(root: Cell, hash: &UInt256, name: &str) -> Result<Cell> {
+    let proof = MerkleProof::construct_from_cell(root)
+        .map_err(|err| proof_invalid(format!("can't read {} proof: {}", name, err)))?;
+    if &proof.hash != hash {
+        return Err(proof_invalid(format!(
+            "{} proof has hash {} but {} expected", name, proof.hash.to_hex_string(), hash.to_hex_string()
+        )))
+    }
+    let root = proof.proof.virtualize(1);
+    if root.repr_hash() != proof.hash {
+        return Err(proof_invalid(format!("{} proof hash does not match its contents", name)))
+    }
+    Ok(root)
+}
+
+// state hash from state update of proven block
+fn check_block_proof(root: Cell, block_id: &BlockIdExt) -> Result<UInt256> {
+    let block = Block::construct_from_cell(check_merkle_proof(root, block_id.root_hash(), "block")?)?;
+    Ok(block.read_state_update()?.new_hash)
+}
+
+fn check_state_proof(root: Cell, state_hash: &UInt256) -> Result<ShardStateUnsplit> {
+    ShardStateUnsplit::construct_from_cell(check_merkle_proof(root, state_hash, "state")?)
+}
+
+fn read_proof_roots(data: &[u8], name: &str) -> Result<Vec<Cell>> {
+    let roots = read_boc(data)
+        .map_err(|err| proof_invalid(format!("can't read {}: {}", name, err)))?
+        .roots;
+    if roots.len() != 2 {
+        return Err(proof_invalid(format!("{} must contain 2 roots but has {}", name, roots.len())))
+    }
+    Ok(roots)
+}
+
+/// Verification of lite server answers with Merkle proofs
+struct ProofCheck {
+    trusted_block: Option<BlockIdExt>
+}
+
+impl ProofCheck {
+
+    // proofs are checked if --verify or --trusted-block is given
+    fn extract(params: &mut Vec<String>) -> Result<Option<Self>> {
+        let verify = extract_flag(params, &["--verify"]);
+        let trusted_block = match extract_option(params, "--trusted-block")? {
+            Some(block) => Some(parse_blockid(Some(block), "trusted block id")?),
+            None => None
+        };
+        if !verify && trusted_block.is_none() {
+            return Ok(None)
+        }
+        Ok(Some(Self { trusted_block }))
+    }
+
+    // answer is requested for trusted block if --block is not given
+    fn target_block(proof_check: &Option<Self>, block: Option<BlockIdExt>) -> Option<BlockIdExt> {
+        block.or_else(|| proof_check.as_ref().and_then(|proof_check| proof_check.trusted_block.clone()))
+    }
+
+    // without trusted block proofs are only checked against root hash given by the same server
+    fn status(&self) -> &'static str {
+        match self.trusted_block {
+            Some(_) => "trusted",
+            None => "self_consistent"
+        }
+    }
+
+    // answer must be given for requested and trusted blocks, proofs are checked against it
+    fn check_block_id(&self, block_id: &BlockIdExt, requested: Option<&BlockIdExt>) -> Result<()> {
+        for expected in self.trusted_block.iter().chain(requested) {
+            if expected != block_id {
+                return Err(proof_invalid(format!("answer is for block {} but {} expected", block_id, expected)))
+            }
+        }
+        Ok(())
+    }
+
+    fn check_config(
+        &self,
+        config_info: &ton::lite_server::ConfigInfo,
+        requested: Option<&BlockIdExt>
+    ) -> Result<ConfigParams> {
+        let block_id = config_info.id();
+        self.check_block_id(block_id, requested)?;
+        let state_proof = read_single_root_boc(&config_info.state_proof().0)
+            .map_err(|err| proof_invalid(format!("can't read state proof: {}", err)))?;
+        let state_hash = check_block_proof(state_proof, block_id)?;
+        let config_proof = read_single_root_boc(&config_info.config_proof().0)
+            .map_err(|err| proof_invalid(format!("can't read config proof: {}", err)))?;
+        let extra = check_state_proof(config_proof, &state_hash)?.read_custom()?
+            .ok_or_else(|| proof_invalid("config proof does not contain McStateExtra".to_string()))?;
+        Ok(extra.config)
+    }
+
+    fn check_account(
+        &self,
+        account_state: &ton::lite_server::AccountState,
+        requested: &BlockIdExt,
+        address: &MsgAddressInt
+    ) -> Result<()> {
+        let block_id = account_state.id();
+        let shard_block_id = account_state.shardblk();
+        self.check_block_id(block_id, Some(requested))?;
+        if shard_block_id != block_id {
+            // shard block is proven by masterchain state
+            let roots = read_proof_roots(&account_state.shard_proof().0, "shard proof")?;
+            let state_hash = check_block_proof(roots[0].clone(), block_id)?;
+            let extra = check_state_proof(roots[1].clone(), &state_hash)?.read_custom()?
+                .ok_or_else(|| proof_invalid("shard proof does not contain McStateExtra".to_string()))?;
+            let shard = extra.shards().find_shard(shard_block_id.shard())?
+                .ok_or_else(|| proof_invalid(format!("shard block {} not found in masterchain", shard_block_id)))?;
+            if shard.block_id() != shard_block_id {
+                return Err(proof_invalid(format!(
+                    "masterchain refers to shard block {} but answer is for {}", shard.block_id(), shard_block_id
+                )))
+            }
+        }
+        let roots = read_proof_roots(&account_state.proof().0, "account proof")?;
+        let state_hash = check_block_proof(roots[0].clone(), shard_block_id)?;
+        let accounts = check_state_proof(roots[1].clone(), &state_hash)?.read_accounts()?;
+        let proven_hash = accounts.account(&address.address())?
+            .map(|shard_account| shard_account.account_cell().repr_hash());
+        let state = &account_state.state().0;
+        let answer_hash = match state.is_empty() {
+            true => None,
+            false => Some(read_single_root_boc(state)?.repr_hash())
+        };
+        if proven_hash != answer_hash {
+            return Err(proof_invalid("account state does not match its proof".to_string()))
+        }
+        Ok(())
+    }
+}
+
+/// ControlClient
+struct ControlClient{
+    config: AdnlConsoleConfigJson,
+    adnl_config: AdnlClientConfig,
+    // None if connection is broken, it is restored by the next query
+    adnl: Option<AdnlClient>,
+    timeout: Option<Duration>,
+    // output of long running commands is printed as json lines
+    json: bool,
+}
+
+impl ControlClient {
+    const RECONNECT_ATTEMPTS: u32 = 5;
+    // delay before the next attempt, doubled after each one
+    const RECONNECT_DELAY: Duration = Duration::from_millis(500);
+
+    /// Connect to server
+    async fn connect(mut config: AdnlConsoleConfigJson, timeout: Option<Duration>, json: bool) -> Result<Self> {
+        let client_config = config.config.take()
+            .ok_or_else(|| ConsoleError::Config("config must contain \"config\" section".to_string()))?;
+        let (_, adnl_config) = AdnlClientConfig::from_json_config(client_config)
+            .map_err(|err| ConsoleError::Config(err.to_string()))?;
+        let adnl = Self::connect_adnl(&adnl_config, timeout).await?;
+        Ok(Self {
+            config,
+            adnl_config,
+            adnl: Some(adnl),
+            timeout,
+            json
+        })
+    }
+
+    async fn connect_adnl(config: &AdnlClientConfig, timeout: Option<Duration>) -> Result<AdnlClient> {
+        let result = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, AdnlClient::connect(config)).await
+                .map_err(|_| ConsoleError::Timeout(format!("no connection in {:?}", timeout)))?,
+            None => AdnlClient::connect(config).await
+        };
+        result.map_err(|err| ConsoleError::Connection(err.to_string()).into())
+    }
+
+    /// Drops current connection and connects again with growing delay between attempts
+    async fn reconnect(&mut self) -> Result<()> {
+        self.disconnect().await;
+        let mut delay = Self::RECONNECT_DELAY;
+        let mut attempt = 1;
+        loop {
+            match Self::connect_adnl(&self.adnl_config, self.timeout).await {
+                Ok(adnl) => {
+                    self.adnl = Some(adnl);
+                    return Ok(())
+                }
+                Err(err) if attempt < Self::RECONNECT_ATTEMPTS => {
+                    log::warn!("reconnect attempt {} failed: {}, next one in {:?}", attempt, err, delay);
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(err) => return Err(err)
+            }
+        }
+    }
+
+    async fn disconnect(&mut self) {
+        if let Some(adnl) = self.adnl.take() {
+            adnl.shutdown().await.ok();
+        }
+    }
+
+    /// Shutdown client
+    async fn shutdown(mut self) -> Result<()> {
+        match self.adnl.take() {
+            Some(adnl) => adnl.shutdown().await,
+            None => Ok(())
+        }
+    }
+
+    async fn command(&mut self, cmd: &str) -> Result<(serde_json::Value, Vec<u8>)> {
+        let mut result = shell_words::split(cmd)
+            .map_err(|err| ConsoleError::Params(format!("can't parse command {}: {}", cmd, err)))?;
+        self.resolve_block(&mut result).await?;
+        let mut params = result.iter();
+        let name = params.next().ok_or_else(|| ConsoleError::Params("empty command".to_string()))?;
+        match name.as_str() {
+            "getstats" |
+            "getstatsnew" if params.clone().any(|param| param == "--watch") => {
+                self.process_stats_watch(params).await
+            }
+            name => match self.command_local(name, params.clone()).await {
+                Some(result) => result,
+                None => self.process_command(name, params).await
+            }
+        }
+    }
+
+    // @input <command>
+    // @output help for all commands or for the given one
+    async fn process_help<Q: ToString>(&mut self, mut params: impl Iterator<Item = Q>) -> Result<(serde_json::Value, Vec<u8>)> {
+        let help = help(params.next().map(|name| name.to_string()).as_deref())?;
+        Ok((help.clone().into(), help.into_bytes()))
+    }
+
+    async fn process_command<Q: ToString>(
+        &mut self,
         name: &str,
         params: impl Iterator<Item = Q> + Clone
-    ) -> Result<(String, Vec<u8>)> {
+    ) -> Result<(serde_json::Value, Vec<u8>)> {
         let query = command_send(name, params.clone())?;
+        let answer = self.send_query(&query, READ_COMMANDS.contains(&name)).await;
+        // account from the block state is loaded by lite server query
+        let answer = match answer {
+            Err(err) if name == "getaccount" && params.clone().any(|param| param.to_string() == "--block") =>
+                return Err(lite_server_error(err, "getAccountState")),
+            answer => answer?
+        };
+        match command_receive(name, answer, params) {
+            Err(answer) => fail!("Wrong response to {:?}: {:?}", query, answer),
+            Ok(result) => Ok(result)
+        }
+    }
+
+    /// Runs control query command given by one line
+    async fn process_command_line(&mut self, line: &str) -> Result<(serde_json::Value, Vec<u8>)> {
+        let mut params = line.split_whitespace();
+        let name = params.next().unwrap_or_default();
+        self.process_command(name, params).await
+    }
+
+    /// Sends read query to server and returns answer if it is not an error
+    async fn query(&mut self, query: &TLObject) -> Result<TLObject> {
+        self.send_query(query, true).await
+    }
+
+    /// Sends query to server, it is repeated after reconnect only if it is idempotent
+    async fn send_query(&mut self, query: &TLObject, idempotent: bool) -> Result<TLObject> {
         let boxed = ControlQuery {
-            data: ton::bytes(serialize_boxed(&query)?)
+            data: ton::bytes(serialize_boxed(query)?)
         };
         #[cfg(feature = "telemetry")]
         let tag = tag_from_bare_object(&boxed);
@@ -563,18 +2070,168 @@ impl ControlClient {
             #[cfg(feature = "telemetry")]
             tag
         };
-        let answer = self.adnl.query(&boxed).await
-            .map_err(|err| error!("Error receiving answer: {}", err))?;
+        let answer = match self.query_adnl(&boxed).await {
+            // connection is restored transparently for read queries, other ones are not repeated
+            // because they may be already processed by the node, the same is for timed out queries;
+            // broken connection is restored by the next query anyway
+            Err(err) if idempotent && matches!(err.downcast_ref::<ConsoleError>(), Some(ConsoleError::Connection(_))) => {
+                log::warn!("connection is broken: {}, reconnecting", err);
+                self.reconnect().await?;
+                self.query_adnl(&boxed).await?
+            }
+            answer => answer?
+        };
         match answer.downcast::<ControlQueryError>() {
-            Err(answer) => match command_receive(name, answer, params) {
-                Err(answer) => fail!("Wrong response to {:?}: {:?}", query, answer),
-                Ok(result) => Ok(result)
+            Err(answer) => Ok(answer),
+            Ok(error) => Err(ConsoleError::ControlQuery {
+                query: format!("{:?}", query),
+                code: *error.code(),
+                message: error.message().to_string()
+            }.into())
+        }
+    }
+
+    async fn query_adnl(&mut self, query: &TaggedTlObject) -> Result<TLObject> {
+        if self.adnl.is_none() {
+            self.reconnect().await?;
+        }
+        let adnl = self.adnl.as_mut().ok_or_else(|| ConsoleError::Connection("not connected".to_string()))?;
+        let result = match self.timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, adnl.query(query)).await {
+                Ok(result) => result,
+                Err(_) => {
+                    // late answer must not be taken for the answer to the next query
+                    self.disconnect().await;
+                    return Err(ConsoleError::Timeout(format!("no answer in {:?}", timeout)).into())
+                }
+            }
+            None => adnl.query(query).await
+        };
+        match result {
+            Ok(answer) => Ok(answer),
+            Err(err) => {
+                self.disconnect().await;
+                Err(ConsoleError::Connection(err.to_string()).into())
+            }
+        }
+    }
+
+    /// Replaces masterchain seqno in --block option with full block id
+    async fn resolve_block(&mut self, params: &mut [String]) -> Result<()> {
+        if let Some(index) = params.iter().position(|param| param == "--block") {
+            if let Some(Ok(seqno)) = params.get(index + 1).map(|param| u32::from_str(param)) {
+                params[index + 1] = self.lookup_mc_block(seqno).await?.to_string();
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds masterchain block id by seqno
+    async fn lookup_mc_block(&mut self, seqno: u32) -> Result<BlockIdExt> {
+        let query = TLObject::new(ton::rpc::lite_server::LookupBlock {
+            mode: 1,
+            id: ton::ton_node::blockid::BlockId {
+                workchain: -1,
+                shard: SHARD_FULL as i64,
+                seqno: seqno as i32
+            },
+            lt: None,
+            utime: None
+        });
+        let answer = self.query(&query).await.map_err(|err| lite_server_error(err, "lookupBlock"))?;
+        let header = downcast::<ton::lite_server::BlockHeader>(answer)?;
+        Ok(header.id().clone())
+    }
+
+    /// Loads current config from masterchain state
+    async fn load_config(&mut self) -> Result<ConfigParams> {
+        self.load_config_at(BlockIdExt::default()).await
+    }
+
+    /// Loads config from the state of given masterchain block, default id means the last one
+    async fn load_config_at(&mut self, id: BlockIdExt) -> Result<ConfigParams> {
+        let query = TLObject::new(ton::rpc::lite_server::GetConfigAll {
+            mode: 0,
+            id
+        });
+        let config_info = downcast::<ton_api::ton::lite_server::ConfigInfo>(self.query(&query).await?)?;
+        parse_config_proof(&config_info.config_proof().0)
+    }
+
+    /// Loads account from the last shard state, None if account does not exist
+    async fn load_account(&mut self, address: &str) -> Result<Option<Account>> {
+        let account_address = AccountAddress { account_address: address.to_string() };
+        let query = TLObject::new(ton::rpc::raw::GetShardAccountState {account_address});
+        match downcast::<ShardAccountState>(self.query(&query).await?)? {
+            ShardAccountState::Raw_ShardAccountNone => Ok(None),
+            ShardAccountState::Raw_ShardAccountState(account_state) => {
+                let shard_account = ShardAccount::construct_from_bytes(&account_state.shard_account)?;
+                Ok(Some(shard_account.read_account()?))
             }
-            Ok(error) => fail!("Error response to {:?}: {:?}", query, error),
         }
     }
 
-    async fn process_recover_stake<Q: ToString>(&mut self, mut params: impl Iterator<Item = Q>) -> Result<(String, Vec<u8>)> {
+    /// Loads elector contract persistent data, elector address is taken from config param 1
+    async fn load_elector(&mut self, config: &ConfigParams) -> Result<ElectorData> {
+        let address = format!("-1:{}", config.elector_address()?.to_hex_string());
+        let account = self.load_account(&address).await?
+            .ok_or_else(|| error!("elector account {} not found", address))?;
+        let data = account.get_data()
+            .ok_or_else(|| error!("elector account {} has no data", address))?;
+        ElectorData::construct_from_cell(data)
+    }
+
+    /// Election id and stake expire time of open elections using config params 15, 34 and elector data
+    async fn election_times(&mut self) -> Result<(ton::int, ton::int)> {
+        let config = self.load_config().await?;
+        let elector = self.load_elector(&config).await?;
+        let elections = elector.current
+            .filter(|elections| !elections.finished && !elections.failed)
+            .ok_or_else(|| error!("no elections are open now"))?;
+        let now = now() as u32;
+        if now >= elections.elect_close {
+            fail!("elections {} were closed at {}", elections.elect_at, elections.elect_close)
+        }
+        let vset = config.validator_set()?;
+        if elections.elect_at < vset.utime_until() {
+            fail!(
+                "elections {} are for the round which has already started, current validator set is active until {}",
+                elections.elect_at, vset.utime_until()
+            )
+        }
+        let p15 = config.elector_params()?;
+        let expire_at = elections.elect_at + p15.validators_elected_for + p15.elections_start_before
+            + p15.elections_end_before + p15.stake_held_for;
+        log::trace!(
+            "election id {} min stake {} total stake {} stake held until {}",
+            elections.elect_at, elections.min_stake, elections.total_stake, expire_at
+        );
+        Ok((elections.elect_at as ton::int, expire_at as ton::int))
+    }
+
+    // @input [--check [--force]] [--value nanotokens] [--msg-file file] [--send] <recover-query.boc>
+    // @output recover-query.boc
+    async fn process_recover_stake<Q: ToString>(&mut self, params: impl Iterator<Item = Q>) -> Result<(serde_json::Value, Vec<u8>)> {
+        let mut params = params.map(|param| param.to_string()).collect::<Vec<_>>();
+        let check = extract_flag(&mut params, &["--check"]);
+        let force = extract_flag(&mut params, &["--force"]);
+        let transfer = WalletTransfer::extract(
+            &mut params, "--value", Some(WalletTransfer::RECOVER_VALUE), "recover-msg.boc"
+        )?;
+        let mut params = params.into_iter();
+        let mut result = serde_json::Map::new();
+        if check {
+            let wallet = parse_any(self.config.wallet_id.as_ref(), "wallet_id", parse_elector_wallet)?;
+            let config = self.load_config().await?;
+            let credit = self.load_elector(&config).await?.credit(&UInt256::from_slice(&wallet));
+            if credit == 0 {
+                if !force {
+                    fail!("nothing to recover: elector has no credit for wallet {}", hex::encode(&wallet))
+                }
+                result.insert("warning".to_string(), "nothing to recover, elector has no credit for the wallet".into());
+            }
+            result.insert("recoverable".to_string(), serde_json::json!(credit));
+        }
         let query_id = now() as u64;
         // recover-stake.fif
         let mut data = 0x47657424u32.to_be_bytes().to_vec();
@@ -586,93 +2243,976 @@ impl ControlClient {
         let data = ton_types::write_boc(&body)?;
         let path = params.next().map(|path| path.to_string()).unwrap_or("recover-query.boc".to_string());
         std::fs::write(&path, &data)?;
-        Ok((format!("Message body is {} saved to path {}", base64::encode(&data), path), data))
+        result.insert("message_body".to_string(), base64::encode(&data).into());
+        result.insert("path".to_string(), path.into());
+        let result = result.into();
+        match transfer {
+            Some(transfer) => {
+                let elector = MsgAddressInt::from_str(ELECTOR_ADDRESS)?;
+                self.process_wallet_transfer(&transfer, &elector, body, result).await
+            }
+            None => Ok((result, data))
+        }
     }
 
-    // @input elect_time expire_time <validator-query.boc>
+    // @input [--dry-run|--plan] [--perm-key hash] [--adnl-key hash] [--stake nanotokens [--msg-file file] [--send]]
+    //        <elect_time expire_time | auto> <validator-query.boc>
     // @output validator-query.boc
-    async fn process_election_bid<Q: ToString>(&mut self, mut params: impl Iterator<Item = Q>) -> Result<(String, Vec<u8>)> {
-        let wallet_id = parse_any(self.config.wallet_id.as_ref(), "wallet_id", |value| {
-            match value.strip_prefix("-1:") {
-                Some(stripped) => Ok(hex::decode(stripped)?),
-                None => fail!("use masterchain wallet")
+    async fn process_election_bid<Q: ToString>(&mut self, params: impl Iterator<Item = Q>) -> Result<(serde_json::Value, Vec<u8>)> {
+        let mut params = params.map(|param| param.to_string()).collect::<Vec<_>>();
+        let dry_run = extract_flag(&mut params, &["--dry-run", "--plan"]);
+        let transfer = WalletTransfer::extract(&mut params, "--stake", None, "validator-msg.boc")?;
+        let perm_key = match extract_option(&mut params, "--perm-key")? {
+            Some(key) => Some(parse_int256(Some(key), "perm_key")?),
+            None => None
+        };
+        let adnl_key = match extract_option(&mut params, "--adnl-key")? {
+            Some(key) => Some(parse_int256(Some(key), "adnl_key")?),
+            None => None
+        };
+        let mut params = params.into_iter().peekable();
+        let wallet_id = parse_any(self.config.wallet_id.as_ref(), "wallet_id", parse_elector_wallet)?;
+        let (elect_time, expire_time) = if params.peek().map(|param| param.as_str()) == Some("auto") {
+            params.next();
+            self.election_times().await?
+        } else {
+            let elect_time = parse_int(params.next(), "elect_time")?;
+            if elect_time <= 0 {
+                return Err(ConsoleError::Params("<elect-utime> must be a positive integer".to_string()).into())
             }
-        })?;
-        let elect_time = parse_int(params.next(), "elect_time")?;
-        if elect_time <= 0 {
-            fail!("<elect-utime> must be a positive integer")
+            let expire_time = parse_int(params.next(), "expire_time")?;
+            if expire_time <= elect_time {
+                return Err(ConsoleError::Params("<expire-utime> must be a grater than elect_time".to_string()).into())
+            }
+            (elect_time, expire_time)
+        };
+        let max_factor = self.config.max_factor
+            .ok_or_else(|| ConsoleError::Config("you must give max_factor as real".to_string()))?;
+        if !(1.0..=100.0).contains(&max_factor) {
+            return Err(ConsoleError::Config("<max-factor> must be a real number 1..100".to_string()).into())
         }
-        let elect_time_str = elect_time.to_string();
-        let expire_time = parse_int(params.next(), "expire_time")?;
-        if expire_time <= elect_time {
-            fail!("<expire-utime> must be a grater than elect_time")
+        let bid = ElectionBid {
+            elect_time,
+            expire_time,
+            max_factor: (max_factor * 65536.0) as u32,
+            wallet_id,
+            perm_key,
+            adnl_key
+        };
+        let path = params.next().unwrap_or_else(|| "validator-query.boc".to_string());
+        if dry_run {
+            return bid.plan(&path, transfer.as_ref())
         }
+        let elect_time_str = elect_time.to_string();
         let expire_time_str = expire_time.to_string();
-        let max_factor = self.config.max_factor.ok_or_else(|| error!("you must give max_factor as real"))?;
-        if max_factor < 1.0 || max_factor > 100.0 {
-            fail!("<max-factor> must be a real number 1..100")
-        }
-        let max_factor = (max_factor * 65536.0) as u32;
 
-        let (s, perm) = self.process_command("newkey", Vec::<String>::new().iter()).await?;
-        log::trace!("{}", s);
-        let perm_str = hex::encode_upper(&perm);
+        // keys given explicitly are supposed to be already registered by previous bid
+        let perm_str = match &bid.perm_key {
+            Some(key) => hex::encode_upper(key.as_slice()),
+            None => {
+                let (s, perm) = self.process_command("newkey", Vec::<String>::new().iter()).await?;
+                log::trace!("{}", s);
+                hex::encode_upper(&perm)
+            }
+        };
 
         let (s, pub_key) = self.process_command("exportpub", [&perm_str].iter()).await?;
         log::trace!("{}", s);
 
-        let (s, _) = self.process_command("addpermkey", [&perm_str, &elect_time_str, &expire_time_str].iter()).await?;
-        log::trace!("{}", s);
+        if bid.perm_key.is_none() {
+            let (s, _) = self.process_command("addpermkey", [&perm_str, &elect_time_str, &expire_time_str].iter()).await?;
+            log::trace!("{}", s);
 
-        let (s, _) = self.process_command("addtempkey", [&perm_str, &perm_str, &expire_time_str].iter()).await?;
+            let (s, _) = self.process_command("addtempkey", [&perm_str, &perm_str, &expire_time_str].iter()).await?;
+            log::trace!("{}", s);
+        }
+
+        let adnl = match &bid.adnl_key {
+            Some(key) => key.as_slice().to_vec(),
+            None => {
+                let (s, adnl) = self.process_command("newkey", Vec::<String>::new().iter()).await?;
+                log::trace!("{}", s);
+                let adnl_str = hex::encode_upper(&adnl);
+
+                let (s, _) = self.process_command("addadnl", [&adnl_str, "0"].iter()).await?;
+                log::trace!("{}", s);
+                adnl
+            }
+        };
+        // given ADNL address must be bound to new perm key too
+        if bid.adnl_key.is_none() || bid.perm_key.is_none() {
+            let adnl_str = hex::encode_upper(&adnl);
+            let (s, _) = self.process_command("addvalidatoraddr", [&perm_str, &adnl_str, &elect_time_str].iter()).await?;
+            log::trace!("{}", s);
+        }
+
+        let data = bid.request(&adnl);
+        let data_str = hex::encode_upper(&data);
+        log::trace!("data to sign {}", data_str);
+        let (s, signature) = self.process_command("sign", [&perm_str, &data_str].iter()).await?;
         log::trace!("{}", s);
+        Ed25519KeyOption::from_public_key(&pub_key[..].try_into()?)
+            .verify(&data, &signature)?;
+
+        let body = bid.signed_body(&pub_key, &adnl, signature)?;
+        log::trace!("message body {}", body);
+        let data = ton_types::write_boc(&body)?;
+        std::fs::write(&path, &data)?;
+        let result = serde_json::json!({
+            "message_body": base64::encode(&data),
+            "path": path,
+        });
+        match transfer {
+            Some(transfer) => {
+                let elector = MsgAddressInt::from_str(ELECTOR_ADDRESS)?;
+                self.process_wallet_transfer(&transfer, &elector, body, result).await
+            }
+            None => Ok((result, data))
+        }
+    }
+
+    /// Wraps message body into wallet submitTransaction external message
+    async fn process_wallet_transfer(
+        &mut self,
+        transfer: &WalletTransfer,
+        dest: &MsgAddressInt,
+        payload: Cell,
+        mut result: serde_json::Value
+    ) -> Result<(serde_json::Value, Vec<u8>)> {
+        let wallet = parse_any(self.config.wallet_id.as_ref(), "wallet_id", parse_address)?;
+        let (pub_key, secret) = self.wallet_key()?;
+        let pub_key = match pub_key {
+            Some(pub_key) => pub_key,
+            None => {
+                let key_hash = self.config.wallet_key_hash.clone().unwrap_or_default();
+                let (s, pub_key) = self.process_command("exportpub", [&key_hash].iter()).await?;
+                log::trace!("{}", s);
+                pub_key
+            }
+        };
+        let call = submit_transaction_call(&pub_key, dest, transfer.value, payload)?;
+        let hash = call.clone().into_cell()?.repr_hash();
+        let signature = match secret {
+            Some(secret) => secret.sign(hash.as_slice())?,
+            None => {
+                let key_hash = self.config.wallet_key_hash.clone().unwrap_or_default();
+                let (s, signature) = self.process_command(
+                    "sign", [&key_hash, &hex::encode_upper(hash.as_slice())].iter()
+                ).await?;
+                log::trace!("{}", s);
+                signature
+            }
+        };
+        Ed25519KeyOption::from_public_key(&pub_key[..].try_into()?)
+            .verify(hash.as_slice(), &signature)?;
+        let mut body = BuilderData::new();
+        body.append_bit_one()?;
+        body.append_raw(&signature, signature.len() * 8)?;
+        body.append_builder(&call)?;
+        let header = ExternalInboundMessageHeader::new(Default::default(), wallet);
+        let message = Message::with_ext_in_header_and_body(header, SliceData::load_builder(body)?);
+        let data = write_boc(&message.serialize()?)?;
+        std::fs::write(&transfer.path, &data)
+            .map_err(|err| error!("Can't write wallet message to file {}: {}", transfer.path, err))?;
+        let mut wallet_message = serde_json::json!({
+            "message": base64::encode(&data),
+            "path": transfer.path,
+            "sent": false,
+        });
+        if transfer.send {
+            let (s, _) = self.process_command("sendmessage", [&transfer.path].iter()).await?;
+            log::trace!("{}", s);
+            wallet_message["sent"] = true.into();
+        }
+        result["wallet_message"] = wallet_message;
+        Ok((result, data))
+    }
+
+    /// Wallet public and private keys from keys file if it is configured,
+    /// otherwise the keys are supposed to live in the node and are used by wallet_key_hash
+    fn wallet_key(&self) -> Result<(Option<Vec<u8>>, Option<Arc<dyn KeyOption>>)> {
+        if let Some(path) = self.config.wallet_keys.as_ref() {
+            let keys = std::fs::read_to_string(path)
+                .map_err(|err| error!("Can't read wallet keys file {}: {}", path, err))?;
+            let keys = serde_json::from_str::<WalletKeysJson>(&keys)
+                .map_err(|err| error!("Can't parse wallet keys file {}: {}", path, err))?;
+            let secret = hex::decode(&keys.secret)?;
+            let secret = Ed25519KeyOption::from_private_key(secret.as_slice().try_into()?)?;
+            let pub_key = secret.pub_key()?.to_vec();
+            if hex::decode(&keys.public)? != pub_key {
+                fail!("public key in wallet keys file {} does not match secret key", path)
+            }
+            Ok((Some(pub_key), Some(secret)))
+        } else if self.config.wallet_key_hash.is_some() {
+            Ok((None, None))
+        } else {
+            fail!("you must give wallet_keys or wallet_key_hash in config to build wallet message")
+        }
+    }
+
+    // @input <wallet address>
+    // @output elector state report for the wallet
+    async fn process_elections<Q: ToString>(&mut self, mut params: impl Iterator<Item = Q>) -> Result<(serde_json::Value, Vec<u8>)> {
+        let wallet = match params.next() {
+            Some(wallet) => parse_elector_wallet(&wallet.to_string())?,
+            None => parse_any(self.config.wallet_id.as_ref(), "wallet_id", parse_elector_wallet)?
+        };
+        let wallet = UInt256::from_slice(&wallet);
+        let config = self.load_config().await?;
+        let elector = self.load_elector(&config).await?;
+        let elections = match &elector.current {
+            Some(elections) => {
+                let participant = elections.participants.iter()
+                    .find(|participant| participant.wallet == wallet)
+                    .map(|participant| serde_json::json!({
+                        "pub_key": participant.pub_key.to_hex_string(),
+                        "stake": participant.stake,
+                        "max_factor": participant.max_factor as f64 / 65536.0,
+                        "adnl_addr": participant.adnl_addr.to_hex_string(),
+                        "time": participant.time,
+                    }));
+                serde_json::json!({
+                    "election_id": elections.elect_at,
+                    "elect_close": elections.elect_close,
+                    "failed": elections.failed,
+                    "finished": elections.finished,
+                    "min_stake": elections.min_stake,
+                    "total_stake": elections.total_stake,
+                    "participants_count": elections.participants.len(),
+                    "min_elected_stake": elections.min_elected_stake(&config)?,
+                    "participant": participant.is_some(),
+                    "our_stake": participant,
+                })
+            }
+            None => serde_json::Value::Null
+        };
+        let past_elections = elector.past_elections.iter().map(|past_elections| {
+            let frozen = past_elections.frozen.iter()
+                .filter(|frozen| frozen.wallet == wallet)
+                .map(|frozen| serde_json::json!({
+                    "pub_key": frozen.pub_key.to_hex_string(),
+                    "weight": frozen.weight,
+                    "stake": frozen.stake,
+                    "banned": frozen.banned,
+                }))
+                .collect::<Vec<_>>();
+            serde_json::json!({
+                "election_id": past_elections.elect_id,
+                "unlocked_at": past_elections.unlocked_at,
+                "stake_held": past_elections.stake_held,
+                "vset_hash": past_elections.vset_hash.to_hex_string(),
+                "total_stake": past_elections.total_stake,
+                "bonuses": past_elections.bonuses,
+                "our_frozen": frozen,
+            })
+        }).collect::<Vec<_>>();
+        let report = serde_json::json!({
+            "wallet": format!("-1:{}", wallet.to_hex_string()),
+            "open_election_id": elector.current.as_ref().map_or(0, |elections| elections.elect_at),
+            "active_election_id": elector.active_id,
+            "active_hash": elector.active_hash.to_hex_string(),
+            "unowned_grams": elector.grams,
+            "elections": elections,
+            "past_elections": past_elections,
+            "credit": elector.credit(&wallet),
+        });
+        let data = format!("{:#}", report).into_bytes();
+        Ok((report, data))
+    }
+
+    /// Loads config contract persistent data, its address is taken from config param 0
+    async fn load_config_contract(&mut self, config: &ConfigParams) -> Result<(MsgAddressInt, ConfigContractData)> {
+        let address = config_address(config)?;
+        let account = self.load_account(&address.to_string()).await?
+            .ok_or_else(|| error!("config account {} not found", address))?;
+        let data = account.get_data()
+            .ok_or_else(|| error!("config account {} has no data", address))?;
+        Ok((address, ConfigContractData::construct_from_cell(data)?))
+    }
+
+    // @input index param.json expire_at [--critical] [--if-hash-equal hash]
+    //        [--value nanotokens [--msg-file file] [--send]] <config-proposal.boc>
+    // @output config-proposal.boc
+    async fn process_config_proposal<Q: ToString>(&mut self, params: impl Iterator<Item = Q>) -> Result<(serde_json::Value, Vec<u8>)> {
+        let mut params = params.map(|param| param.to_string()).collect::<Vec<_>>();
+        let critical = extract_flag(&mut params, &["--critical"]);
+        let if_hash_equal = match extract_option(&mut params, "--if-hash-equal")? {
+            Some(hash) => Some(parse_int256(Some(hash), "if_hash_equal")?),
+            None => None
+        };
+        let transfer = WalletTransfer::extract(&mut params, "--value", None, "config-proposal-msg.boc")?;
+        let mut params = params.into_iter();
+        let index = parse_int(params.next(), "index")?;
+        let param_file = parse_any(params.next(), "param.json", |value| Ok(value.to_string()))?;
+        let expire_at = parse_int(params.next(), "expire_at")?;
+        if expire_at <= now() {
+            fail!("<expire_at> must be in the future")
+        }
+        let path = params.next().unwrap_or_else(|| "config-proposal.boc".to_string());
+
+        // param is given in the same format as getconfig prints it, e.g. {"p15": {...}}
+        let param = std::fs::read_to_string(&param_file)
+            .map_err(|err| error!("Can't read config param json file {} : {}", param_file, err))?;
+        let param = serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&param)
+            .map_err(|err| error!("Can't parse config param json file: {}", err))?;
+        let config = ton_block_json::parse_config(&param)
+            .map_err(|err| error!("Can't parse config param json file: {}", err))?;
+        let param_value = config_param_cell(&config, index, "config param json file")?;
+
+        let proposal = ConfigProposal {
+            param_id: index,
+            param_value: Some(param_value),
+            if_hash_equal
+        };
+        // create-config-proposal.fif
+        let query_id = now() as u64;
+        let mut body = BuilderData::new();
+        body.append_u32(0x6e565052)?;
+        body.append_u64(query_id)?;
+        body.append_u32(expire_at as u32)?;
+        body.checked_append_reference(proposal.serialize()?)?;
+        body.append_bit_bool(critical)?;
+        let body = body.into_cell()?;
+        log::trace!("message body {}", body);
+        let data = write_boc(&body)?;
+        std::fs::write(&path, &data)
+            .map_err(|err| error!("Can't write config proposal to file {}: {}", path, err))?;
+        let result = serde_json::json!({
+            "message_body": base64::encode(&data),
+            "path": path,
+            "proposal_hash": proposal.serialize()?.repr_hash().to_hex_string(),
+        });
+        match transfer {
+            Some(transfer) => {
+                let config = self.load_config().await?;
+                let dest = config_address(&config)?;
+                self.process_wallet_transfer(&transfer, &dest, body, result).await
+            }
+            None => Ok((result, data))
+        }
+    }
 
-        let (s, adnl) = self.process_command("newkey", Vec::<String>::new().iter()).await?;
+    // @input key_hash proposal_hash [--send] <config-vote.boc>
+    // @output config-vote.boc
+    async fn process_config_vote<Q: ToString>(&mut self, params: impl Iterator<Item = Q>) -> Result<(serde_json::Value, Vec<u8>)> {
+        let mut params = params.map(|param| param.to_string()).collect::<Vec<_>>();
+        let send = extract_flag(&mut params, &["--send"]);
+        let mut params = params.into_iter();
+        let key_hash = parse_int256(params.next(), "key_hash")?;
+        let proposal_hash = parse_int256(params.next(), "proposal_hash")?;
+        let path = params.next().unwrap_or_else(|| "config-vote.boc".to_string());
+        let key_hash = hex::encode_upper(key_hash.as_slice());
+
+        let (s, pub_key) = self.process_command("exportpub", [&key_hash].iter()).await?;
         log::trace!("{}", s);
-        let adnl_str = hex::encode_upper(&adnl);
+        let config = self.load_config().await?;
+        let vset = config.validator_set()?;
+        let index = vset.list().iter()
+            .position(|validator| validator.public_key.as_slice() == pub_key.as_slice())
+            .ok_or_else(|| error!("key {} is not in current validator set", key_hash))?;
 
-        let (s, _) = self.process_command("addadnl", [&adnl_str, "0"].iter()).await?;
+        // config-proposal-vote-req.fif
+        let mut data = 0x566f7465u32.to_be_bytes().to_vec();
+        data.extend_from_slice(&(index as u16).to_be_bytes());
+        data.extend_from_slice(proposal_hash.as_slice());
+        let (s, signature) = self.process_command("sign", [&key_hash, &hex::encode_upper(&data)].iter()).await?;
         log::trace!("{}", s);
+        Ed25519KeyOption::from_public_key(&pub_key[..].try_into()?)
+            .verify(&data, &signature)?;
+
+        // config-proposal-vote-signed.fif: tag, signature and the signed data with its own tag
+        let mut body = BuilderData::new();
+        body.append_u32(0x566f7465)?;
+        body.append_raw(&signature, signature.len() * 8)?;
+        body.append_raw(&data, data.len() * 8)?;
+        let (dest, _) = self.load_config_contract(&config).await?;
+        let header = ExternalInboundMessageHeader::new(Default::default(), dest);
+        let message = Message::with_ext_in_header_and_body(header, SliceData::load_builder(body)?);
+        let data = write_boc(&message.serialize()?)?;
+        std::fs::write(&path, &data)
+            .map_err(|err| error!("Can't write config vote message to file {}: {}", path, err))?;
+        let mut result = serde_json::json!({
+            "validator_index": index,
+            "message": base64::encode(&data),
+            "path": path,
+            "sent": false,
+        });
+        if send {
+            let (s, _) = self.process_command("sendmessage", [&path].iter()).await?;
+            log::trace!("{}", s);
+            result["sent"] = true.into();
+        }
+        Ok((result, data))
+    }
+
+    // @output active config proposals
+    async fn process_config_proposals<Q: ToString>(&mut self, _params: impl Iterator<Item = Q>) -> Result<(serde_json::Value, Vec<u8>)> {
+        let config = self.load_config().await?;
+        let (address, data) = self.load_config_contract(&config).await?;
+        let proposals = data.proposals.iter().map(|status| {
+            let value = match &status.proposal.param_value {
+                Some(value) => serde_json::json!({
+                    "hash": value.repr_hash().to_hex_string(),
+                    "boc": base64::encode(&write_boc(value)?),
+                }),
+                None => serde_json::Value::Null
+            };
+            Ok(serde_json::json!({
+                "hash": status.hash.to_hex_string(),
+                "param_id": status.proposal.param_id,
+                "param_value": value,
+                "if_hash_equal": status.proposal.if_hash_equal.as_ref().map(|hash| hash.to_hex_string()),
+                "expires": status.expires,
+                "critical": status.critical,
+                "voters": status.voters,
+                "remaining_weight": status.remaining_weight,
+                "validator_set_id": status.validator_set_id.to_hex_string(),
+                "rounds_remaining": status.rounds_remaining,
+                "wins": status.wins,
+                "losses": status.losses,
+            }))
+        }).collect::<Result<Vec<_>>>()?;
+        let result = serde_json::json!({
+            "config_address": address.to_string(),
+            "seqno": data.seqno,
+            "public_key": data.public_key.to_hex_string(),
+            "proposals": proposals,
+        });
+        let data = format!("{:#}", result).into_bytes();
+        Ok((result, data))
+    }
+
+    /// Loads config from masterchain block given by seqno, full id or "last",
+    /// or from local zerostate or config json file
+    // block syntax goes first, so a file named as block must be given with path (e.g. ./last)
+    async fn load_config_from(&mut self, source: &str) -> Result<ConfigParams> {
+        let id = if source == "last" {
+            BlockIdExt::default()
+        } else if let Ok(seqno) = u32::from_str(source) {
+            self.lookup_mc_block(seqno).await?
+        } else if let Ok(id) = parse_blockid(Some(source), "block_id") {
+            id
+        } else if std::path::Path::new(source).is_file() {
+            return read_config_json(source)
+        } else {
+            return Err(ConsoleError::Params(
+                format!("{} is neither masterchain block seqno, block id, last nor config json file", source)
+            ).into())
+        };
+        self.load_config_at(id).await
+    }
+
+    // @input block_id (block_id | config.json) [param_number...]
+    // @output changed config params
+    async fn process_config_diff<Q: ToString>(&mut self, params: impl Iterator<Item = Q>) -> Result<(serde_json::Value, Vec<u8>)> {
+        let mut params = params.map(|param| param.to_string());
+        let from = parse_any(params.next(), "block_id", |value| Ok(value.to_string()))?;
+        let to = parse_any(params.next(), "block_id or config.json", |value| Ok(value.to_string()))?;
+        let numbers = params
+            .map(|param| parse_any(Some(param), "param_number", |value| Ok(u32::from_str(value)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let old = config_to_json(&self.load_config_from(&from).await?, None)?;
+        let new = config_to_json(&self.load_config_from(&to).await?, None)?;
+        let (old, new) = match (old, new) {
+            (serde_json::Value::Object(old), serde_json::Value::Object(new)) => (old, new),
+            _ => fail!("config must be rendered as json object")
+        };
+        let mut keys = old.keys().chain(new.keys())
+            .filter(|key| numbers.is_empty() || numbers.iter().any(|number| **key == format!("p{}", number)))
+            .cloned()
+            .collect::<Vec<_>>();
+        keys.sort_by_key(|key| u32::from_str(key.trim_start_matches('p')).unwrap_or(u32::MAX));
+        keys.dedup();
+
+        let mut diff = serde_json::Map::new();
+        for key in keys {
+            let status = match (old.get(&key), new.get(&key)) {
+                (Some(old), Some(new)) => {
+                    let mut changes = vec![];
+                    json_diff(&key, old, new, &mut changes);
+                    if changes.is_empty() {
+                        continue
+                    }
+                    serde_json::json!({"status": "changed", "changes": changes})
+                }
+                (Some(old), None) => serde_json::json!({"status": "removed", "from": old}),
+                (None, Some(new)) => serde_json::json!({"status": "added", "to": new}),
+                (None, None) => continue
+            };
+            diff.insert(key, status);
+        }
+        let result = serde_json::json!({
+            "from": from,
+            "to": to,
+            "changed": diff.len(),
+            "params": diff,
+        });
+        let data = format!("{:#}", result).into_bytes();
+        Ok((result, data))
+    }
+
+    /// Loads config of the node
+    async fn load_node_config(&mut self) -> Result<serde_json::Value> {
+        let query = TLObject::new(ton::rpc::engine::validator::GetConfig);
+        let config = downcast::<ton_api::ton::engine::validator::JsonConfig>(self.query(&query).await?)?;
+        serde_json::from_str::<serde_json::Value>(config.data())
+            .map_err(|err| error!("Can't parse node config: {}", err))
+    }
+
+    /// Loads config of the node with its keys
+    async fn load_node_keys(&mut self) -> Result<Vec<NodeKey>> {
+        let config = self.load_node_config().await?;
+        self.node_keys(&config).await
+    }
+
+    /// Keys of the node config, unknown expire time is taken from validator sets
+    async fn node_keys(&mut self, config: &serde_json::Value) -> Result<Vec<NodeKey>> {
+        let mut keys = NodeKey::from_config(config);
+        if keys.iter().any(|key| key.expire_at.is_none() && key.election_date.is_some()) {
+            let config = self.load_config().await?;
+            let current = config.validator_set()?;
+            let current = (current.utime_since() as i64, current.utime_until() as i64);
+            let next = config.next_validator_set()?;
+            let next = match next.list().is_empty() {
+                true => None,
+                false => Some((next.utime_since() as i64, next.utime_until() as i64))
+            };
+            for key in &mut keys {
+                key.set_vset_expire_at(current, next);
+            }
+        }
+        Ok(keys)
+    }
+
+    // @input [--delete-expired]
+    // @output keys with their roles and expire time
+    async fn process_keys<Q: ToString>(&mut self, params: impl Iterator<Item = Q>) -> Result<(serde_json::Value, Vec<u8>)> {
+        let mut params = params.map(|param| param.to_string()).collect::<Vec<_>>();
+        let delete_expired = extract_flag(&mut params, &["--delete-expired"]);
+        let keys = self.load_node_keys().await?;
+        let mut deleted = vec![];
+        if delete_expired {
+            // temp keys and addresses are unbound before their permanent key
+            let mut expired = keys.iter().filter(|key| key.expired()).collect::<Vec<_>>();
+            expired.sort_by_key(|key| key.role == NodeKey::PERM);
+            for key in expired {
+                if let Some(query) = key.delete_query() {
+                    self.process_command_line(&query).await?;
+                    deleted.push(query);
+                }
+            }
+        }
+        let list = keys.iter().map(|key| {
+            let mut json = serde_json::to_value(key)?;
+            json["expired"] = key.expired().into();
+            Ok(json)
+        }).collect::<Result<Vec<_>>>()?;
+        let result = serde_json::json!({
+            "keys": list,
+            "deleted": deleted,
+        });
+        let data = format!("{:#}", result).into_bytes();
+        Ok((result, data))
+    }
+
+    // @input [--dry-run] [--margin seconds]
+    // @output keys expiration timeline and rotated keys
+    async fn process_rotate<Q: ToString>(&mut self, params: impl Iterator<Item = Q>) -> Result<(serde_json::Value, Vec<u8>)> {
+        const DEFAULT_MARGIN: i64 = 3600;
+        let mut params = params.map(|param| param.to_string()).collect::<Vec<_>>();
+        let dry_run = extract_flag(&mut params, &["--dry-run"]);
+        let margin = match extract_option(&mut params, "--margin")? {
+            Some(margin) => parse_any(Some(margin), "--margin <seconds>", |value| Ok(i64::from_str(value)?))?,
+            None => DEFAULT_MARGIN
+        };
+        let config = self.load_config().await?;
+        let mut vsets = vec![("current", config.validator_set()?)];
+        let next = config.next_validator_set()?;
+        if !next.list().is_empty() {
+            vsets.push(("next", next));
+        }
+        let node_config = self.load_node_config().await?;
+        let keys = self.node_keys(&node_config).await?;
+        // Rust node keeps no temp keys, only ADNL addresses are rotated
+        let temp_keys = NodeKey::has_temp_keys(&node_config);
+        let now = now() as i64;
+
+        let mut validators = vec![];
+        let mut queries = vec![];
+        for perm in keys.iter().filter(|key| key.role == NodeKey::PERM && !key.expired()) {
+            let (_, pub_key) = self.process_command("exportpub", [&perm.key_hash].iter()).await?;
+            let vset = vsets.iter().find(|(_, vset)| {
+                vset.list().iter().any(|validator| validator.public_key.as_slice() == pub_key.as_slice())
+            });
+            let (vset_name, vset) = match vset {
+                Some(vset) => vset,
+                None => continue
+            };
+            // keys must live as long as the perm key, or until the validator set is replaced
+            // if expire time of the perm key is unknown
+            let required_until = perm.expire_at.unwrap_or(vset.utime_until() as i64);
+            // key with unknown expire time is bound until the validator set is replaced
+            let latest = |role: &str| keys.iter()
+                .filter(|key| key.role == role && key.perm_key.as_ref() == Some(&perm.key_hash))
+                .map(|key| key.expire_at.unwrap_or(vset.utime_until() as i64))
+                .max();
+            let temp_until = latest(NodeKey::TEMP);
+            let adnl_until = latest(NodeKey::ADNL);
+            // new keys expire together with the perm key, so keys of the perm key expiring
+            // within the margin are not rotated: they would be added again on every run
+            let perm_expiring = required_until - now < margin;
+            let needs_rotation = |until: Option<i64>| !perm_expiring && until.map_or(true, |until| until < required_until);
+            let rotate_temp = temp_keys && needs_rotation(temp_until);
+            let rotate_adnl = needs_rotation(adnl_until);
+            if rotate_temp {
+                queries.push(format!("newkey -> addtempkey {} <new key> {}", perm.key_hash, required_until));
+            }
+            if rotate_adnl {
+                queries.push(format!("newkey -> addadnl <new key> 0 -> addvalidatoraddr {} <new key> {}", perm.key_hash, required_until));
+            }
+            if !dry_run {
+                if rotate_temp {
+                    let (_, key) = self.process_command("newkey", std::iter::empty::<String>()).await?;
+                    let key = hex::encode_upper(&key);
+                    self.process_command_line(&format!("addtempkey {} {} {}", perm.key_hash, key, required_until)).await?;
+                }
+                if rotate_adnl {
+                    let (_, key) = self.process_command("newkey", std::iter::empty::<String>()).await?;
+                    let key = hex::encode_upper(&key);
+                    self.process_command_line(&format!("addadnl {} 0", key)).await?;
+                    self.process_command_line(&format!("addvalidatoraddr {} {} {}", perm.key_hash, key, required_until)).await?;
+                }
+            }
+            validators.push(serde_json::json!({
+                "perm_key": perm.key_hash,
+                "validator_set": vset_name,
+                "required_until": required_until,
+                "perm_key_expiring": perm_expiring,
+                "temp_key_until": temp_until,
+                "adnl_addr_until": adnl_until,
+                "rotate_temp_key": rotate_temp,
+                "rotate_adnl_addr": rotate_adnl,
+            }));
+        }
+
+        let mut timeline = keys.iter()
+            .map(|key| serde_json::json!({
+                "expire_at": key.expire_at,
+                "expires_in": key.expire_at.map(|expire_at| expire_at - now),
+                "expired": key.expired(),
+                "key_hash": key.key_hash,
+                "role": key.role,
+                "perm_key": key.perm_key,
+            }))
+            .chain(vsets.iter().map(|(name, vset)| serde_json::json!({
+                "expire_at": vset.utime_until(),
+                "expires_in": vset.utime_until() as i64 - now,
+                "validator_set": name,
+            })))
+            .collect::<Vec<_>>();
+        // keys with unknown expire time go last
+        timeline.sort_by_key(|event| (event["expire_at"].is_null(), event["expire_at"].as_i64()));
+
+        let result = serde_json::json!({
+            "dry_run": dry_run,
+            "temp_keys": if temp_keys { "rotated" } else { "not kept by node config (validator_keys layout)" },
+            "validators": validators,
+            "queries": queries,
+            "timeline": timeline,
+        });
+        let data = format!("{:#}", result).into_bytes();
+        Ok((result, data))
+    }
+
+    // @input backup file, password is taken from environment
+    // @output encrypted backup file
+    async fn process_keys_backup<Q: ToString>(&mut self, mut params: impl Iterator<Item = Q>) -> Result<(serde_json::Value, Vec<u8>)> {
+        let path = parse_any(params.next(), "file name", |value| Ok(value.to_string()))?;
+        let keys = self.load_node_keys().await?;
+        let mut secrets = serde_json::Map::new();
+        for key in &keys {
+            if secrets.contains_key(&key.key_hash) {
+                continue
+            }
+            let query = TLObject::new(ton::rpc::engine::validator::ExportPrivateKey {
+                key_hash: parse_int256(Some(&key.key_hash), "key_hash")?
+            });
+            let secret = match downcast::<ton_api::ton::PrivateKey>(self.query(&query).await?)? {
+                ton::PrivateKey::Pk_Ed25519(secret) => secret.key,
+                _ => fail!("key {} is not ed25519 key", key.key_hash)
+            };
+            secrets.insert(key.key_hash.clone(), hex::encode(secret.as_slice()).into());
+        }
+        let plain = serde_json::json!({
+            "keys": keys,
+            "secrets": secrets,
+        });
+        let backup = KeysBackup::encrypt(plain.to_string().as_bytes(), &KeysBackup::password()?)?;
+        let data = format!("{:#}", backup).into_bytes();
+        std::fs::write(&path, &data)
+            .map_err(|err| error!("Can't write keys backup to file {}: {}", path, err))?;
+        Ok((serde_json::json!({"path": path, "keys": keys.len(), "secrets": secrets.len()}), data))
+    }
+
+    // @input backup file, password is taken from environment
+    // @output imported keys and bindings
+    async fn process_keys_restore<Q: ToString>(&mut self, mut params: impl Iterator<Item = Q>) -> Result<(serde_json::Value, Vec<u8>)> {
+        let path = parse_any(params.next(), "file name", |value| Ok(value.to_string()))?;
+        let backup = std::fs::read_to_string(&path)
+            .map_err(|err| ConsoleError::Params(format!("Can't read keys backup {}: {}", path, err)))?;
+        let backup = serde_json::from_str::<serde_json::Value>(&backup)
+            .map_err(|err| ConsoleError::Params(format!("Can't parse keys backup {}: {}", path, err)))?;
+        let plain = serde_json::from_slice::<serde_json::Value>(&KeysBackup::decrypt(&backup, &KeysBackup::password()?)?)
+            .map_err(|err| error!("Can't parse decrypted keys backup: {}", err))?;
+        // every key is tried, outcome is reported per key: present keys are skipped
+        // and failed ones do not stop the rest
+        let mut failed = 0;
+        let mut imported = vec![];
+        for (key_hash, secret) in plain["secrets"].as_object().into_iter().flatten() {
+            let status = if self.process_command("exportpub", [key_hash].iter()).await.is_ok() {
+                serde_json::json!({"key_hash": key_hash, "status": "present"})
+            } else {
+                let secret = secret.as_str().unwrap_or_default();
+                match self.process_command("importkey", [secret].iter()).await {
+                    Ok((answer, _)) if answer["key_hash"].as_str().map_or(false, |hash| hash.eq_ignore_ascii_case(key_hash)) =>
+                        serde_json::json!({"key_hash": key_hash, "status": "imported"}),
+                    Ok((answer, _)) => {
+                        let err = error!("imported key hash {} does not match backup", answer["key_hash"]);
+                        serde_json::json!({"key_hash": key_hash, "status": "failed", "error": error_to_json(&err)})
+                    }
+                    Err(err) => serde_json::json!({"key_hash": key_hash, "status": "failed", "error": error_to_json(&err)})
+                }
+            };
+            if status["status"] == "failed" {
+                failed += 1;
+            }
+            imported.push(status);
+        }
+        // permanent keys must be bound before their temp keys and addresses
+        let mut keys = serde_json::from_value::<Vec<NodeKey>>(plain["keys"].clone())?;
+        keys.sort_by_key(|key| key.role != NodeKey::PERM);
+        let present = self.load_node_keys().await?;
+        let mut bound = vec![];
+        for key in keys {
+            let mut status = serde_json::json!({"key_hash": key.key_hash, "role": key.role});
+            if key.expired() {
+                status["status"] = "expired".into();
+            } else if present.iter().any(|bound| bound.key_hash == key.key_hash && bound.role == key.role) {
+                status["status"] = "present".into();
+            } else {
+                let result = match key.add_query() {
+                    Ok(query) => {
+                        status["query"] = query.clone().into();
+                        self.process_command_line(&query).await
+                    }
+                    Err(err) => Err(err)
+                };
+                match result {
+                    Ok(_) => status["status"] = "bound".into(),
+                    Err(err) => {
+                        failed += 1;
+                        status["status"] = "failed".into();
+                        status["error"] = error_to_json(&err);
+                    }
+                }
+            }
+            bound.push(status);
+        }
+        let result = serde_json::json!({
+            "imported": imported,
+            "bound": bound,
+            "failed": failed,
+        });
+        if failed > 0 {
+            fail!("{} keys are not restored: {:#}", failed, result)
+        }
+        let data = format!("{:#}", result).into_bytes();
+        Ok((result, data))
+    }
+
+    // @input --watch seconds [--max-lag seconds] [--expect-validator] [--hook command]
+    // @output changed stats on every poll
+    async fn process_stats_watch<Q: ToString>(&mut self, params: impl Iterator<Item = Q>) -> Result<(serde_json::Value, Vec<u8>)> {
+        use std::io::IsTerminal;
+        let mut params = params.map(|param| param.to_string()).collect::<Vec<_>>();
+        let watch = StatsWatch::extract(&mut params)?;
+        let highlight = !self.json && std::io::stdout().is_terminal();
+        let mut last = serde_json::Map::new();
+        let mut last_block: Option<(i64, std::time::Instant)> = None;
+        let mut raised = Vec::<String>::new();
+        let mut samples = 0;
+        let mut interrupts = interrupts();
+        loop {
+            // failed poll is reported and watching goes on
+            let stats = match self.process_command("getstatsnew", std::iter::empty::<String>()).await {
+                Ok((stats, _)) => Some(stats),
+                Err(err) => {
+                    if self.json {
+                        println!("{}", serde_json::json!({"time": now(), "ok": false, "error": error_to_json(&err)}));
+                    } else {
+                        println!("Error getting stats: {}", err);
+                    }
+                    None
+                }
+            };
+            if let Some(stats) = stats {
+                self.watch_sample(&watch, stats, highlight, &mut last, &mut last_block, &mut raised).await?;
+                samples += 1;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(watch.interval) => (),
+                _ = interrupts.changed() => break
+            }
+        }
+        let result = serde_json::json!({
+            "samples": samples,
+            "alerts": raised,
+        });
+        Ok((result, vec![]))
+    }
+
+    // prints changes, rates and alerts of the polled stats, fires hook on raised alerts
+    async fn watch_sample(
+        &self,
+        watch: &StatsWatch,
+        stats: serde_json::Value,
+        highlight: bool,
+        last: &mut serde_json::Map<String, serde_json::Value>,
+        last_block: &mut Option<(i64, std::time::Instant)>,
+        raised: &mut Vec<String>
+    ) -> Result<()> {
+        let stats_map = stats.as_object().cloned().unwrap_or_default();
+        let changed = stats_map.iter()
+            .filter(|(key, value)| last.get(*key) != Some(*value))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect::<serde_json::Map<_, _>>();
+
+        // masterchain blocks applied per minute since previous poll
+        let mut rates = serde_json::Map::new();
+        if let Some(seqno) = stats["masterchainblocknumber"].as_i64() {
+            let time = std::time::Instant::now();
+            if let Some((last_seqno, last_time)) = *last_block {
+                let minutes = time.duration_since(last_time).as_secs_f64() / 60.0;
+                if minutes > 0.0 {
+                    rates.insert("mc_blocks_per_minute".to_string(), ((seqno - last_seqno) as f64 / minutes).into());
+                }
+            }
+            *last_block = Some((seqno, time));
+        }
+
+        let alerts = watch.alerts(&stats);
+        if self.json {
+            println!("{}", serde_json::json!({
+                "time": now(),
+                "ok": true,
+                "changed": changed,
+                "rates": rates,
+                "alerts": alerts,
+            }));
+        } else {
+            // colors only in terminal
+            let (value_color, rate_color, alert_color, reset) = match highlight {
+                true => ("\x1b[1;33m", "\x1b[1;36m", "\x1b[1;31m", "\x1b[0m"),
+                false => ("", "", "", "")
+            };
+            for (key, value) in &changed {
+                match last.get(key) {
+                    Some(old) => println!("{}: {} -> {}{}{}", key, old, value_color, value, reset),
+                    None => println!("{}: {}", key, value)
+                }
+            }
+            for (key, value) in &rates {
+                println!("{}: {}{:.2}{}", key, rate_color, value.as_f64().unwrap_or_default(), reset);
+            }
+            for alert in &alerts {
+                println!("{}alert: {}{}", alert_color, alert, reset);
+            }
+        }
+        // hook fires when alert is raised, not on every poll while it lasts
+        for alert in alerts.iter().filter(|alert| !raised.contains(alert)) {
+            watch.fire(alert, &stats).await?;
+        }
+        *raised = alerts;
+        *last = stats_map;
+        Ok(())
+    }
 
-        let (s, _) = self.process_command("addvalidatoraddr", [&perm_str, &adnl_str, &elect_time_str].iter()).await?;
-        log::trace!("{}", s);
+    // @input recorder, poll interval
+    async fn run_recorder(&mut self, recorder: StatsRecorder, interval: Duration) -> Result<i32> {
+        let mut interrupts = interrupts();
+        loop {
+            let mut sample = serde_json::json!({"time": now()});
+            let stats = self.process_command("getstatsnew", std::iter::empty::<String>()).await;
+            let sessions = self.process_command("getconsensusstats", std::iter::empty::<String>()).await;
+            match (stats, sessions) {
+                (Ok((stats, _)), Ok((sessions, _))) => {
+                    sample["ok"] = true.into();
+                    sample["stats"] = stats;
+                    sample["sessions"] = sessions;
+                }
+                (Err(err), _) | (_, Err(err)) => {
+                    sample["ok"] = false.into();
+                    sample["error"] = error_to_json(&err);
+                }
+            }
+            recorder.append(&sample)?;
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => (),
+                _ = interrupts.changed() => return Ok(0)
+            }
+        }
+    }
 
-        // validator-elect-req.fif
-        let mut data = 0x654C5074u32.to_be_bytes().to_vec();
-        data.extend_from_slice(&elect_time.to_be_bytes());
-        data.extend_from_slice(&max_factor.to_be_bytes());
-        data.extend_from_slice(&wallet_id);
-        data.extend_from_slice(&adnl);
-        let data_str = hex::encode_upper(&data);
-        log::trace!("data to sign {}", data_str);
-        let (s, signature) = self.process_command("sign", [&perm_str, &data_str].iter()).await?;
-        log::trace!("{}", s);
-        Ed25519KeyOption::from_public_key(&pub_key[..].try_into()?)
-            .verify(&data, &signature)?;
+    /// Polls node stats and consensus stats, failed queries are reported by ton_node_up metric
+    async fn collect_metrics(&mut self) -> String {
+        let mut metrics = Metrics::default();
+        let mut up = true;
+        match self.process_command("getstatsnew", std::iter::empty::<String>()).await {
+            Ok((stats, _)) => metrics.add_json("ton_node", "", &stats),
+            Err(err) => {
+                log::warn!("Can't get stats: {}", err);
+                up = false;
+            }
+        }
+        match self.process_command("getconsensusstats", std::iter::empty::<String>()).await {
+            Ok((serde_json::Value::Object(sessions), _)) => for (session, stats) in sessions {
+                metrics.add_session(&session, &stats)
+            }
+            Ok(_) => (),
+            Err(err) => {
+                log::warn!("Can't get consensus stats: {}", err);
+                up = false;
+            }
+        }
+        metrics.add("ton_node_up", "", if up { 1.0 } else { 0.0 });
+        metrics.add("ton_node_scrape_timestamp_seconds", "", now() as f64);
+        metrics.render()
+    }
 
-        let query_id = now() as u64;
-        // validator-elect-signed.fif
-        let mut data = 0x4E73744Bu32.to_be_bytes().to_vec();
-        data.extend_from_slice(&query_id.to_be_bytes());
-        data.extend_from_slice(&pub_key);
-        data.extend_from_slice(&elect_time.to_be_bytes());
-        data.extend_from_slice(&max_factor.to_be_bytes());
-        data.extend_from_slice(&adnl);
-        let len = data.len() * 8;
-        let mut body = BuilderData::with_raw(data, len)?;
-        let len = signature.len() * 8;
-        body.checked_append_reference(BuilderData::with_raw(signature, len)?.into_cell()?)?;
-        let body = body.into_cell()?;
-        log::trace!("message body {}", body);
-        let data = ton_types::write_boc(&body)?;
-        let path = params.next().map(|path| path.to_string()).unwrap_or("validator-query.boc".to_string());
-        std::fs::write(&path, &data)?;
-        Ok((format!("Message body is {} saved to path {}", base64::encode(&data), path), data))
+    // @input listen address, poll interval
+    async fn run_exporter(&mut self, listen: &str, interval: Duration) -> Result<i32> {
+        let listener = tokio::net::TcpListener::bind(listen).await
+            .map_err(|err| ConsoleError::Params(format!("Can't listen on {}: {}", listen, err)))?;
+        log::info!("serving metrics on {}", listen);
+        let metrics = Arc::new(std::sync::Mutex::new(String::new()));
+        tokio::spawn(serve_metrics(listener, metrics.clone()));
+        let mut interrupts = interrupts();
+        loop {
+            let text = self.collect_metrics().await;
+            if let Ok(mut metrics) = metrics.lock() {
+                *metrics = text;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => (),
+                _ = interrupts.changed() => return Ok(0)
+            }
+        }
     }
 
     // @input index zerostate.json <config-param.boc>
     // @output config-param.boc
-    async fn process_config_param<Q: ToString>(&mut self, mut params: impl Iterator<Item = Q>) -> Result<(String, Vec<u8>)> {
+    async fn process_config_param<Q: ToString>(&mut self, mut params: impl Iterator<Item = Q>) -> Result<(serde_json::Value, Vec<u8>)> {
         let index = parse_int(params.next(), "index")?;
         if index < 0 {
             fail!("<index> must not be a negative integer")
@@ -688,15 +3228,10 @@ impl ControlClient {
         let zerostate = ton_block_json::parse_state(&zerostate)
             .map_err(|err| error!("Can't parse read zerostate json file: {}", err))?;
 
-        let key = SliceData::load_builder(index.write_to_new_cell()?)?;
-        let config_param_cell = zerostate.read_custom()
+        let config = zerostate.read_custom()
             .map_err(|err| error!("Can't read McStateExtra from zerostate: {}", err))?
-            .ok_or_else(|| error!("Can't find McStateExtra in zerostate"))?
-            .config().config_params.get(key)
-            .map_err(|err| error!("Can't read config param {} from zerostate: {}", index, err))?
-            .ok_or_else(|| error!("Can't find config param {} in zerostate", index))?
-            .reference_opt(0)
-            .ok_or_else(|| error!("Can't parse config param {}: wrong format - no reference", index))?;
+            .ok_or_else(|| error!("Can't find McStateExtra in zerostate"))?;
+        let config_param_cell = config_param_cell(config.config(), index, "zerostate")?;
 
         let data = write_boc(&config_param_cell)
             .map_err(|err| error!("Can't serialize config param {}: {}", index, err))?;
@@ -704,15 +3239,761 @@ impl ControlClient {
         std::fs::write(&path, &data)
             .map_err(|err| error!("Can't write config param {} to file {}: {}", index, path, err))?;
 
-        Ok((format!("Config param {} saved to path {}", index, path), data))
+        Ok((serde_json::json!({"index": index, "path": path}), data))
+    }
+}
+
+/// Script file runner: one command per line, `#` comments, `$name = command` captures
+/// the result into variable, `$name` and `$name.field` are replaced with captured values,
+/// `set -e`/`set +e` switches stopping on the first failed command
+struct Script {
+    variables: serde_json::Map<String, serde_json::Value>,
+    // raw answers of commands: key hash for newkey, public key for exportpub, signature for sign
+    data: std::collections::HashMap<String, Vec<u8>>,
+    fail_fast: bool
+}
+
+impl Script {
+    fn new(fail_fast: bool) -> Self {
+        Self {
+            variables: serde_json::Map::new(),
+            data: std::collections::HashMap::new(),
+            fail_fast
+        }
+    }
+
+    /// Runs script and prints json summary, returns exit code
+    async fn run(&mut self, client: &mut ControlClient, path: &str, json: bool) -> i32 {
+        let mut summary = serde_json::json!({
+            "script": path,
+            "executed": 0,
+            "failed": 0,
+        });
+        let exit_code = match self.run_lines(client, path, json, &mut summary).await {
+            Ok(exit_code) => exit_code,
+            Err(err) => {
+                summary["error"] = error_to_json(&err);
+                error_code(&err)
+            }
+        };
+        summary["ok"] = (exit_code == 0).into();
+        summary["variables"] = self.variables.clone().into();
+        println!("{}", summary);
+        exit_code
+    }
+
+    async fn run_lines(
+        &mut self,
+        client: &mut ControlClient,
+        path: &str,
+        json: bool,
+        summary: &mut serde_json::Value
+    ) -> Result<i32> {
+        let script = std::fs::read_to_string(path)
+            .map_err(|err| ConsoleError::Params(format!("Can't read script file {}: {}", path, err)))?;
+        let mut exit_code = 0;
+        let (mut executed, mut failed) = (0, 0);
+        for (number, line) in script.lines().enumerate() {
+            let line = line.trim();
+            match line {
+                "" => continue,
+                _ if line.starts_with('#') => continue,
+                "set -e" => self.fail_fast = true,
+                "set +e" => self.fail_fast = false,
+                _ => {
+                    let (variable, command) = match line.strip_prefix('$').and_then(|line| line.split_once('=')) {
+                        Some((variable, command)) => (Some(variable.trim()), command.trim()),
+                        None => (None, line)
+                    };
+                    let result = match self.expand(command) {
+                        Ok(command) => client.command(&command).await,
+                        Err(err) => Err(err)
+                    };
+                    print_result(command, &result, json);
+                    executed += 1;
+                    match result {
+                        Ok((result, data)) => if let Some(variable) = variable {
+                            self.variables.insert(variable.to_string(), result);
+                            self.data.insert(variable.to_string(), data);
+                        }
+                        Err(err) => {
+                            failed += 1;
+                            if exit_code == 0 {
+                                exit_code = error_code(&err);
+                            }
+                            if self.fail_fast {
+                                summary["stopped_at_line"] = (number + 1).into();
+                                break
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        summary["executed"] = executed.into();
+        summary["failed"] = failed.into();
+        Ok(exit_code)
+    }
+
+    // replaces $name.field with the field of captured json and $name with captured value,
+    // which is raw answer in hex if the command answers with json object
+    fn expand(&self, command: &str) -> Result<String> {
+        let mut result = String::new();
+        let mut rest = command;
+        while let Some(start) = rest.find('$') {
+            result.push_str(&rest[..start]);
+            rest = &rest[start + 1..];
+            let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+                .unwrap_or(rest.len());
+            let mut path = rest[..end].trim_end_matches('.').split('.');
+            rest = &rest[end..];
+            let name = path.next().unwrap_or_default();
+            let mut value = self.variables.get(name)
+                .ok_or_else(|| ConsoleError::Params(format!("variable ${} is not set", name)))?;
+            let mut bare = true;
+            for field in path {
+                bare = false;
+                value = value.get(field)
+                    .ok_or_else(|| ConsoleError::Params(format!("variable ${} has no field {}", name, field)))?;
+            }
+            let value = match value {
+                serde_json::Value::String(value) => value.clone(),
+                // bare $name of json answer is replaced with raw answer in hex
+                serde_json::Value::Object(_) | serde_json::Value::Array(_) if bare => {
+                    match self.data.get(name).filter(|data| !data.is_empty()) {
+                        Some(data) => hex::encode_upper(data),
+                        None => return Err(ConsoleError::Params(
+                            format!("variable ${} has no raw value, give its field as ${}.<field>", name, name)
+                        ).into())
+                    }
+                }
+                value => value.to_string()
+            };
+            result.push_str(&shell_words::quote(&value));
+        }
+        result.push_str(rest);
+        Ok(result)
+    }
+}
+
+/// Key of the server bound to validator as permanent, temp key or ADNL address,
+/// or used as ADNL id of the node
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+struct NodeKey {
+    key_hash: String,
+    role: String,
+    perm_key: Option<String>,
+    election_date: Option<i64>,
+    expire_at: Option<i64>,
+    category: Option<i64>
+}
+
+impl NodeKey {
+    const PERM: &'static str = "perm";
+    const TEMP: &'static str = "temp";
+    const ADNL: &'static str = "adnl";
+    const ADNL_ID: &'static str = "adnl_id";
+
+    fn new(key_id: &serde_json::Value, role: &str, perm_key: Option<&str>) -> Option<Self> {
+        let key = base64::decode(key_id.as_str()?).ok()?;
+        Some(Self {
+            key_hash: hex::encode_upper(key),
+            role: role.to_string(),
+            perm_key: perm_key.map(|key| key.to_string()),
+            election_date: None,
+            expire_at: None,
+            category: None
+        })
+    }
+
+    fn expired(&self) -> bool {
+        self.expire_at.map_or(false, |expire_at| expire_at < now() as i64)
+    }
+
+    // reads keys from node config json, both validator engine layout
+    // with validators list and Rust node layout with validator_keys list are known
+    fn from_config(config: &serde_json::Value) -> Vec<Self> {
+        let mut keys = vec![];
+        for validator in config["validators"].as_array().into_iter().flatten() {
+            let mut perm = match Self::new(&validator["id"], Self::PERM, None) {
+                Some(perm) => perm,
+                None => continue
+            };
+            perm.election_date = validator["election_date"].as_i64();
+            perm.expire_at = validator["expire_at"].as_i64();
+            for temp in validator["temp_keys"].as_array().into_iter().flatten() {
+                if let Some(mut key) = Self::new(&temp["key"], Self::TEMP, Some(&perm.key_hash)) {
+                    key.expire_at = temp["expire_at"].as_i64();
+                    keys.push(key);
+                }
+            }
+            for adnl in validator["adnl_addrs"].as_array().into_iter().flatten() {
+                if let Some(mut key) = Self::new(&adnl["id"], Self::ADNL, Some(&perm.key_hash)) {
+                    key.expire_at = adnl["expire_at"].as_i64();
+                    keys.push(key);
+                }
+            }
+            keys.push(perm);
+        }
+        for validator in config["validator_keys"].as_array().into_iter().flatten() {
+            let mut perm = match Self::new(&validator["validator_key_id"], Self::PERM, None) {
+                Some(perm) => perm,
+                None => continue
+            };
+            perm.election_date = validator["election_id"].as_i64();
+            if let Some(mut key) = Self::new(&validator["validator_adnl_key_id"], Self::ADNL, Some(&perm.key_hash)) {
+                key.election_date = perm.election_date;
+                keys.push(key);
+            }
+            keys.push(perm);
+        }
+        for adnl in config["adnl"].as_array().into_iter().flatten() {
+            if let Some(mut key) = Self::new(&adnl["id"], Self::ADNL_ID, None) {
+                key.category = adnl["category"].as_i64();
+                keys.push(key);
+            }
+        }
+        keys
+    }
+
+    // control query to unbind the key
+    fn delete_query(&self) -> Option<String> {
+        match (self.role.as_str(), &self.perm_key) {
+            (Self::PERM, _) => Some(format!("delpermkey {}", self.key_hash)),
+            (Self::TEMP, Some(perm)) => Some(format!("deltempkey {} {}", perm, self.key_hash)),
+            (Self::ADNL, Some(perm)) => Some(format!("delvalidatoraddr {} {}", perm, self.key_hash)),
+            (Self::ADNL_ID, _) => Some(format!("deladnl {}", self.key_hash)),
+            _ => None
+        }
+    }
+
+    // Rust node layout with validator_keys list keeps no temp keys
+    fn has_temp_keys(config: &serde_json::Value) -> bool {
+        config["validator_keys"].is_null()
+    }
+
+    // Rust node layout has no expire time of perm keys and addresses, they live while
+    // the validator set of their elections (election id is utime_since of the set)
+    fn set_vset_expire_at(&mut self, current: (i64, i64), next: Option<(i64, i64)>) {
+        if self.expire_at.is_some() || (self.role != Self::PERM && self.role != Self::ADNL) {
+            return
+        }
+        let election_date = match self.election_date {
+            Some(election_date) => election_date,
+            None => return
+        };
+        self.expire_at = match next {
+            Some((since, until)) if since == election_date => Some(until),
+            _ if current.0 == election_date => Some(current.1),
+            // validator set of these elections is already replaced by current one
+            _ if election_date < current.0 => Some(current.0),
+            // elections are not finished yet, expire time is not known
+            _ => None
+        };
+    }
+
+    // control query to bind the key again after import
+    fn add_query(&self) -> Result<String> {
+        let perm = || self.perm_key.as_ref()
+            .ok_or_else(|| error!("{} key {} has no permanent key", self.role, self.key_hash));
+        let expire_at = || self.expire_at
+            .ok_or_else(|| error!("{} key {} has unknown expire time", self.role, self.key_hash));
+        match self.role.as_str() {
+            Self::PERM => {
+                let election_date = self.election_date
+                    .ok_or_else(|| error!("perm key {} has unknown election date", self.key_hash))?;
+                Ok(format!("addpermkey {} {} {}", self.key_hash, election_date, expire_at()?))
+            }
+            Self::TEMP => Ok(format!("addtempkey {} {} {}", perm()?, self.key_hash, expire_at()?)),
+            Self::ADNL => Ok(format!("addvalidatoraddr {} {} {}", perm()?, self.key_hash, expire_at()?)),
+            Self::ADNL_ID => Ok(format!("addadnl {} {}", self.key_hash, self.category.unwrap_or_default())),
+            role => fail!("unknown role {} of key {}", role, self.key_hash)
+        }
+    }
+}
+
+/// Password encryption of key backups: PBKDF2-SHA256 key derivation and ChaCha20-Poly1305
+struct KeysBackup;
+
+impl KeysBackup {
+    const PASSWORD_ENV: &'static str = "CONSOLE_BACKUP_PASSWORD";
+    const ROUNDS: u32 = 100_000;
+    const SALT_LEN: usize = 16;
+    const NONCE_LEN: usize = 12;
+
+    fn password() -> Result<String> {
+        env::var(Self::PASSWORD_ENV)
+            .map_err(|_| ConsoleError::Params(format!("set backup password in {} environment variable", Self::PASSWORD_ENV)).into())
+    }
+
+    fn cipher(password: &str, salt: &[u8], rounds: u32) -> chacha20poly1305::ChaCha20Poly1305 {
+        use chacha20poly1305::KeyInit;
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<sha2::Sha256>(password.as_bytes(), salt, rounds, &mut key);
+        chacha20poly1305::ChaCha20Poly1305::new(&key.into())
+    }
+
+    fn encrypt(data: &[u8], password: &str) -> Result<serde_json::Value> {
+        use chacha20poly1305::aead::Aead;
+        use rand::RngCore;
+        let mut salt = [0u8; Self::SALT_LEN];
+        let mut nonce = [0u8; Self::NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let data = Self::cipher(password, &salt, Self::ROUNDS)
+            .encrypt(&nonce.into(), data)
+            .map_err(|err| error!("Can't encrypt keys: {}", err))?;
+        Ok(serde_json::json!({
+            "version": 1,
+            "kdf": "pbkdf2-sha256",
+            "rounds": Self::ROUNDS,
+            "cipher": "chacha20-poly1305",
+            "salt": base64::encode(salt),
+            "nonce": base64::encode(nonce),
+            "data": base64::encode(data),
+        }))
+    }
+
+    // parameters of the backup file are not trusted: only the ones written by encrypt are accepted,
+    // so damaged or hostile file can't make key derivation run for hours
+    fn decrypt(backup: &serde_json::Value, password: &str) -> Result<Vec<u8>> {
+        use chacha20poly1305::aead::Aead;
+        let field = |name: &str, len: usize| -> Result<Vec<u8>> {
+            let value = backup[name].as_str()
+                .ok_or_else(|| ConsoleError::Params(format!("backup has no {}", name)))?;
+            let value = base64::decode(value)
+                .map_err(|err| ConsoleError::Params(format!("backup has wrong {}: {}", name, err)))?;
+            if len != 0 && value.len() != len {
+                return Err(ConsoleError::Params(format!("backup {} must be {} bytes", name, len)).into())
+            }
+            Ok(value)
+        };
+        if backup["rounds"].as_u64() != Some(Self::ROUNDS as u64) {
+            return Err(ConsoleError::Params(
+                format!("backup must be encrypted with {} rounds, but has {}", Self::ROUNDS, backup["rounds"])
+            ).into())
+        }
+        let salt = field("salt", Self::SALT_LEN)?;
+        let nonce = field("nonce", Self::NONCE_LEN)?;
+        let data = field("data", 0)?;
+        Self::cipher(password, &salt, Self::ROUNDS)
+            .decrypt(chacha20poly1305::Nonce::from_slice(&nonce), data.as_slice())
+            .map_err(|_| ConsoleError::Params("Can't decrypt backup: wrong password or damaged file".to_string()).into())
+    }
+}
+
+/// Options of getstats --watch
+struct StatsWatch {
+    interval: Duration,
+    max_lag: Option<i64>,
+    expect_validator: bool,
+    hook: Option<String>
+}
+
+impl StatsWatch {
+    fn extract(params: &mut Vec<String>) -> Result<Self> {
+        let interval = extract_option(params, "--watch")?;
+        let interval = parse_any(interval, "--watch <seconds>", |value| Ok(u64::from_str(value)?))?;
+        if interval == 0 {
+            return Err(ConsoleError::Params("--watch interval must be positive".to_string()).into())
+        }
+        let max_lag = match extract_option(params, "--max-lag")? {
+            Some(max_lag) => Some(parse_any(Some(max_lag), "--max-lag <seconds>", |value| Ok(i64::from_str(value)?))?),
+            None => None
+        };
+        Ok(Self {
+            interval: Duration::from_secs(interval),
+            max_lag,
+            expect_validator: extract_flag(params, &["--expect-validator"]),
+            hook: extract_option(params, "--hook")?
+        })
+    }
+
+    // thresholds crossed by stats sample
+    fn alerts(&self, stats: &serde_json::Value) -> Vec<String> {
+        let mut alerts = vec![];
+        if let (Some(max_lag), Some(lag)) = (self.max_lag, stats["timediff"].as_i64()) {
+            if lag > max_lag {
+                alerts.push(format!("sync lag {} seconds is above {}", lag, max_lag));
+            }
+        }
+        if self.expect_validator && stats["in_current_vset_p34"].as_bool() != Some(true) {
+            alerts.push("node is not in current validator set".to_string());
+        }
+        alerts
+    }
+
+    // runs hook with alert in environment, the watch goes on whatever hook returns
+    async fn fire(&self, alert: &str, stats: &serde_json::Value) -> Result<()> {
+        let hook = match &self.hook {
+            Some(hook) => hook,
+            None => return Err(ConsoleError::Alert(alert.to_string()).into())
+        };
+        let status = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(hook)
+            .env("CONSOLE_ALERT", alert)
+            .env("CONSOLE_STATS", stats.to_string())
+            .status().await;
+        match status {
+            Ok(status) if status.success() => (),
+            Ok(status) => log::warn!("alert hook exited with {}", status),
+            Err(err) => log::warn!("Can't run alert hook: {}", err)
+        }
+        Ok(())
+    }
+}
+
+/// Appends stats samples to JSONL file, the file is rotated to <file>.1 ... <file>.<keep>
+/// when it grows above max size
+struct StatsRecorder {
+    path: String,
+    max_size: u64,
+    keep: usize
+}
+
+impl StatsRecorder {
+    fn append(&self, sample: &serde_json::Value) -> Result<()> {
+        use std::io::Write;
+        let size = std::fs::metadata(&self.path).map(|metadata| metadata.len()).unwrap_or_default();
+        if size >= self.max_size {
+            self.rotate()?;
+        }
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&self.path)
+            .map_err(|err| error!("Can't open stats file {}: {}", self.path, err))?;
+        writeln!(file, "{}", sample)
+            .map_err(|err| error!("Can't write stats file {}: {}", self.path, err))?;
+        Ok(())
+    }
+
+    fn rotate(&self) -> Result<()> {
+        for index in (1..self.keep).rev() {
+            let from = format!("{}.{}", self.path, index);
+            if std::path::Path::new(&from).exists() {
+                std::fs::rename(&from, format!("{}.{}", self.path, index + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, format!("{}.1", self.path))
+            .map_err(|err| error!("Can't rotate stats file {}: {}", self.path, err))?;
+        Ok(())
+    }
+
+    // all files of the recorder from the oldest one
+    fn files(path: &str) -> Vec<String> {
+        let mut files = (1..)
+            .map(|index| format!("{}.{}", path, index))
+            .take_while(|path| std::path::Path::new(path).exists())
+            .collect::<Vec<_>>();
+        files.reverse();
+        files.push(path.to_string());
+        files
+    }
+}
+
+fn percentile(sorted: &[i64], percent: usize) -> Option<i64> {
+    match sorted.len() {
+        0 => None,
+        len => Some(sorted[((len - 1) * percent + 50) / 100])
+    }
+}
+
+/// Summarizes samples written by record command in time window
+fn stats_report(path: &str, from: Option<u64>, to: Option<u64>) -> Result<serde_json::Value> {
+    let (mut samples, mut available, mut validator, mut participated) = (0, 0, 0, 0);
+    let (mut first, mut last) = (None, None);
+    let mut lags = vec![];
+    // session id -> samples, sum and count of own signing rates
+    let mut sessions = std::collections::BTreeMap::<String, (u64, f64, u64)>::new();
+    for file in StatsRecorder::files(path) {
+        let data = std::fs::read_to_string(&file)
+            .map_err(|err| ConsoleError::Params(format!("Can't read stats file {}: {}", file, err)))?;
+        for line in data.lines().filter(|line| !line.trim().is_empty()) {
+            let sample = match serde_json::from_str::<serde_json::Value>(line) {
+                Ok(sample) => sample,
+                Err(err) => {
+                    log::warn!("Skipping broken sample in {}: {}", file, err);
+                    continue
+                }
+            };
+            let time = sample["time"].as_u64().unwrap_or_default();
+            if from.map_or(false, |from| time < from) || to.map_or(false, |to| time > to) {
+                continue
+            }
+            samples += 1;
+            first = first.or(Some(time));
+            last = Some(time);
+            if sample["ok"].as_bool() != Some(true) {
+                continue
+            }
+            available += 1;
+            let stats = &sample["stats"];
+            if let Some(lag) = stats["timediff"].as_i64() {
+                lags.push(lag);
+            }
+            if stats["in_current_vset_p34"].as_bool() == Some(true) {
+                validator += 1;
+                let mut active = false;
+                if let Some(session_stats) = sample["sessions"].as_object() {
+                    for (session, stats) in session_stats {
+                        let (count, rates, rates_count) = sessions.entry(session.clone()).or_default();
+                        *count += 1;
+                        if let Some(rate) = stats["own_signing_rate"].as_f64() {
+                            *rates += rate;
+                            *rates_count += 1;
+                        }
+                        active = true;
+                    }
+                }
+                if active {
+                    participated += 1;
+                }
+            }
+        }
+    }
+    lags.sort_unstable();
+    let ratio = |part: u64, total: u64| if total == 0 { serde_json::Value::Null } else { (part as f64 / total as f64).into() };
+    let sessions = sessions.into_iter().map(|(session, (count, rates, rates_count))| {
+        let own_signing_rate = match rates_count {
+            0 => serde_json::Value::Null,
+            _ => (rates / rates_count as f64).into()
+        };
+        (session, serde_json::json!({"samples": count, "own_signing_rate": own_signing_rate}))
+    }).collect::<serde_json::Map<_, _>>();
+    Ok(serde_json::json!({
+        "samples": samples,
+        "from": first,
+        "to": last,
+        "uptime": ratio(available, samples),
+        "sync_lag": {
+            "p50": percentile(&lags, 50),
+            "p90": percentile(&lags, 90),
+            "p99": percentile(&lags, 99),
+            "max": lags.last(),
+        },
+        "consensus": {
+            "validator_samples": validator,
+            "participation": ratio(participated, validator),
+            "sessions": sessions,
+        },
+    }))
+}
+
+/// Stats in Prometheus text exposition format, all metrics are gauges
+#[derive(Default)]
+struct Metrics {
+    // metric name -> samples with labels
+    samples: std::collections::BTreeMap<String, Vec<String>>,
+    // name with labels of every series, Prometheus rejects duplicates
+    series: std::collections::HashSet<String>
+}
+
+impl Metrics {
+    // text stats with few possible values, exported as info metrics with value label,
+    // other text stats (block ids, hashes, descriptions) are skipped
+    const INFO_METRICS: &'static [&'static str] = &["ton_node_sync_status"];
+
+    // stats keys which differ only in symbols replaced by Self::name give the same series,
+    // only the first of them is exported
+    fn add(&mut self, name: &str, labels: &str, value: f64) {
+        let series = match labels {
+            "" => name.to_string(),
+            labels => format!("{}{{{}}}", name, labels)
+        };
+        if !self.series.insert(series.clone()) {
+            log::warn!("Metric {} is duplicated, skipped", series);
+            return
+        }
+        let sample = format!("{} {}", series, value);
+        self.samples.entry(name.to_string()).or_default().push(sample);
+    }
+
+    // numbers and bools become gauges, allowed strings become info metrics with value label,
+    // nested objects are flattened into metric names
+    fn add_json(&mut self, name: &str, labels: &str, value: &serde_json::Value) {
+        match value {
+            serde_json::Value::Null => (),
+            serde_json::Value::Bool(value) => self.add(name, labels, if *value { 1.0 } else { 0.0 }),
+            serde_json::Value::Number(value) => if let Some(value) = value.as_f64() {
+                self.add(name, labels, value)
+            }
+            serde_json::Value::String(value) => match value.parse::<f64>() {
+                Ok(value) => self.add(name, labels, value),
+                Err(_) => if Self::INFO_METRICS.contains(&name) {
+                    self.add(&format!("{}_info", name), &Self::label(labels, "value", value), 1.0)
+                }
+            }
+            serde_json::Value::Array(values) => for (index, value) in values.iter().enumerate() {
+                self.add_json(name, &Self::label(labels, "index", &index.to_string()), value)
+            }
+            serde_json::Value::Object(map) => for (key, value) in map {
+                self.add_json(&format!("{}_{}", name, Self::name(key)), labels, value)
+            }
+        }
+    }
+
+    // decoded fields of consensus session stats, per validator participation and not decoded
+    // stats are not exported to keep the count of series bounded
+    fn add_session(&mut self, session: &str, stats: &serde_json::Value) {
+        let labels = Self::label("", "session", session);
+        for field in [
+            "catchain_seqno", "rounds", "approved", "rejected", "committed",
+            "own_signing_rate", "average_signing_rate", "own_vs_average"
+        ] {
+            self.add_json(&format!("ton_node_session_{}", field), &labels, &stats[field]);
+        }
+    }
+
+    fn name(key: &str) -> String {
+        key.chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+            .collect()
+    }
+
+    fn label(labels: &str, name: &str, value: &str) -> String {
+        let value = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+        match labels {
+            "" => format!("{}=\"{}\"", name, value),
+            labels => format!("{},{}=\"{}\"", labels, name, value)
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut text = String::new();
+        for (name, samples) in &self.samples {
+            text.push_str(&format!("# TYPE {} gauge\n", name));
+            for sample in samples {
+                text.push_str(sample);
+                text.push('\n');
+            }
+        }
+        text
+    }
+}
+
+/// Serves last collected metrics over http on any path except unknown ones
+async fn serve_metrics(listener: tokio::net::TcpListener, metrics: Arc<std::sync::Mutex<String>>) {
+    loop {
+        let mut socket = match listener.accept().await {
+            Ok((socket, _)) => socket,
+            Err(err) => {
+                log::warn!("Can't accept connection: {}", err);
+                continue
+            }
+        };
+        let metrics = metrics.lock().map(|metrics| metrics.clone()).unwrap_or_default();
+        tokio::spawn(async move {
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+            let mut request = vec![0; 4096];
+            let len = socket.read(&mut request).await.unwrap_or_default();
+            let request = String::from_utf8_lossy(&request[..len]);
+            let path = request.split_whitespace().nth(1).unwrap_or_default();
+            let response = match path {
+                "/" | "/metrics" => format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    metrics.len(), metrics
+                ),
+                _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+            };
+            if let Err(err) = socket.write_all(response.as_bytes()).await {
+                log::warn!("Can't send metrics: {}", err);
+            }
+            socket.shutdown().await.ok();
+        });
+    }
+}
+
+static INTERRUPTS: std::sync::OnceLock<tokio::sync::watch::Receiver<u64>> = std::sync::OnceLock::new();
+
+/// Counter of Ctrl-C presses. Once it is asked for, Ctrl-C does not stop the process:
+/// long running commands stop between queries and interactive mode cancels the command
+/// in progress and closes the connection, so the answer of the dropped query is not
+/// taken for the answer to the next one
+fn interrupts() -> tokio::sync::watch::Receiver<u64> {
+    INTERRUPTS.get_or_init(|| {
+        let (sender, receiver) = tokio::sync::watch::channel(0);
+        tokio::spawn(async move {
+            let mut count = 0;
+            while tokio::signal::ctrl_c().await.is_ok() {
+                count += 1;
+                if sender.send(count).is_err() {
+                    break
+                }
+            }
+        });
+        receiver
+    }).clone()
+}
+
+/// Completion of command names and key hashes seen in the session
+#[derive(Default)]
+struct ReplHelper {
+    key_hashes: std::collections::BTreeSet<String>
+}
+
+impl ReplHelper {
+    // remembers key hashes from the command line and from the answer
+    fn remember(&mut self, command: &str, result: &Result<(serde_json::Value, Vec<u8>)>) {
+        let is_key_hash = |word: &str| word.len() == 64 && word.chars().all(|c| c.is_ascii_hexdigit());
+        for word in command.split_whitespace().filter(|word| is_key_hash(word)) {
+            self.key_hashes.insert(word.to_uppercase());
+        }
+        if let Ok((result, _)) = result {
+            if let Some(key_hash) = result["key_hash"].as_str().filter(|word| is_key_hash(word)) {
+                self.key_hashes.insert(key_hash.to_uppercase());
+            }
+        }
+    }
+}
+
+impl rustyline::completion::Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos].rfind(char::is_whitespace).map_or(0, |index| index + 1);
+        let word = &line[start..pos];
+        let candidates = if start == 0 || line[..start].trim() == "help" {
+            command_names()
+                .filter(|name| name.starts_with(word))
+                .map(|name| name.to_string())
+                .collect()
+        } else {
+            self.key_hashes.iter()
+                .filter(|key_hash| key_hash.starts_with(&word.to_uppercase()))
+                .cloned()
+                .collect()
+        };
+        Ok((start, candidates))
     }
 }
 
+impl rustyline::hint::Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl rustyline::highlight::Highlighter for ReplHelper {}
+
+impl rustyline::validate::Validator for ReplHelper {}
+
+impl rustyline::Helper for ReplHelper {}
+
 #[derive(serde::Deserialize)]
 struct AdnlConsoleConfigJson {
     config: Option<AdnlClientConfigJson>,
     wallet_id: Option<String>,
-    max_factor: Option<f32>
+    max_factor: Option<f32>,
+    wallet_keys: Option<String>,
+    wallet_key_hash: Option<String>
+}
+
+#[derive(serde::Deserialize)]
+struct WalletKeysJson {
+    public: String,
+    secret: String
 }
 
 #[tokio::main]
@@ -738,7 +4019,22 @@ async fn main() {
         .arg(clap::Arg::with_name("TIMEOUT")
             .short("t")
             .long("timeout")
-            .help("timeout in batch mode")
+            .help("timeout of every query to the node in seconds")
+            .takes_value(true)
+            .number_of_values(1))
+        .arg(clap::Arg::with_name("SCRIPT")
+            .short("s")
+            .long("script")
+            .help("run commands from script file")
+            .conflicts_with("COMMANDS")
+            .takes_value(true)
+            .number_of_values(1))
+        .arg(clap::Arg::with_name("KEEP_GOING")
+            .long("keep-going")
+            .help("run all commands in batch mode or script even if some of them fail"))
+        .arg(clap::Arg::with_name("HISTORY")
+            .long("history")
+            .help("history file for interactive mode (~/.console_history by default)")
             .takes_value(true)
             .number_of_values(1))
         .arg(clap::Arg::with_name("VERBOSE")
@@ -749,7 +4045,79 @@ async fn main() {
             .long("json")
             .help("output in json format")
             .takes_value(false))
-        .get_matches();
+        .subcommand(clap::SubCommand::with_name("exporter")
+            .about("serves node stats as Prometheus metrics")
+            .arg(clap::Arg::with_name("LISTEN")
+                .long("listen")
+                .help("address to serve metrics on, e.g. 0.0.0.0:9100")
+                .required(true)
+                .takes_value(true)
+                .number_of_values(1))
+            .arg(clap::Arg::with_name("INTERVAL")
+                .long("interval")
+                .help("stats poll interval in seconds")
+                .default_value("15")
+                .takes_value(true)
+                .number_of_values(1)))
+        .subcommand(clap::SubCommand::with_name("record")
+            .about("records node stats to JSONL file")
+            .arg(clap::Arg::with_name("OUT")
+                .long("out")
+                .help("file to append stats samples to")
+                .required(true)
+                .takes_value(true)
+                .number_of_values(1))
+            .arg(clap::Arg::with_name("INTERVAL")
+                .long("interval")
+                .help("stats poll interval in seconds")
+                .default_value("60")
+                .takes_value(true)
+                .number_of_values(1))
+            .arg(clap::Arg::with_name("MAX_SIZE")
+                .long("max-size")
+                .help("size of file in megabytes to rotate it")
+                .default_value("100")
+                .takes_value(true)
+                .number_of_values(1))
+            .arg(clap::Arg::with_name("KEEP")
+                .long("keep")
+                .help("count of rotated files to keep")
+                .default_value("5")
+                .takes_value(true)
+                .number_of_values(1)))
+        .subcommand(clap::SubCommand::with_name("report")
+            .about("summarizes stats recorded by record subcommand")
+            .arg(clap::Arg::with_name("IN")
+                .long("in")
+                .help("file with stats samples, rotated files are read too")
+                .required(true)
+                .takes_value(true)
+                .number_of_values(1))
+            .arg(clap::Arg::with_name("FROM")
+                .long("from")
+                .help("unixtime of window start")
+                .takes_value(true)
+                .number_of_values(1))
+            .arg(clap::Arg::with_name("TO")
+                .long("to")
+                .help("unixtime of window end")
+                .takes_value(true)
+                .number_of_values(1))
+            .arg(clap::Arg::with_name("LAST")
+                .long("last")
+                .help("window of the last seconds, instead of --from and --to")
+                .conflicts_with_all(&["FROM", "TO"])
+                .takes_value(true)
+                .number_of_values(1)))
+        .get_matches_safe()
+        .unwrap_or_else(|err| match err.kind {
+            clap::ErrorKind::HelpDisplayed | clap::ErrorKind::VersionDisplayed => err.exit(),
+            // wrong arguments are params errors, not other ones
+            _ => {
+                eprintln!("{}", err.message);
+                std::process::exit(ConsoleError::Params(err.message).code())
+            }
+        });
 
     if !args.is_present("JSON") {
         println!(
@@ -762,6 +4130,21 @@ async fn main() {
         );
     }
 
+    let exit_code = match run(&args).await {
+        Ok(exit_code) => exit_code,
+        Err(err) => {
+            if args.is_present("JSON") {
+                println!("{}", serde_json::json!({"ok": false, "error": error_to_json(&err)}));
+            } else {
+                println!("Error: {}", err);
+            }
+            error_code(&err)
+        }
+    };
+    std::process::exit(exit_code)
+}
+
+async fn run(args: &clap::ArgMatches<'_>) -> Result<i32> {
     if args.is_present("VERBOSE") {
         let encoder_boxed = Box::new(log4rs::encode::pattern::PatternEncoder::new("{m}{n}"));
         let console = log4rs::append::console::ConsoleAppender::builder()
@@ -769,44 +4152,210 @@ async fn main() {
             .build();
         let config = log4rs::config::Config::builder()
             .appender(log4rs::config::Appender::builder().build("console", Box::new(console)))
-            .build(log4rs::config::Root::builder().appender("console").build(log::LevelFilter::Trace))
-            .unwrap();
-        log4rs::init_config(config).unwrap();
+            .build(log4rs::config::Root::builder().appender("console").build(log::LevelFilter::Trace))?;
+        log4rs::init_config(config)?;
+    }
+
+    // report needs no connection to the node
+    if let Some(report) = args.subcommand_matches("report") {
+        let parse_time = |name: &str| match report.value_of(name) {
+            Some(value) => parse_any(Some(value), name, |value| Ok(u64::from_str(value)?)).map(Some),
+            None => Ok(None)
+        };
+        let (from, to) = match parse_time("LAST")? {
+            Some(last) => (Some((now() as u64).saturating_sub(last)), None),
+            None => (parse_time("FROM")?, parse_time("TO")?)
+        };
+        let result = stats_report(report.value_of("IN").unwrap_or_default(), from, to);
+        let exit_code = result.as_ref().err().map_or(0, error_code);
+        print_result("report", &result.map(|report| (report, vec![])), args.is_present("JSON"));
+        return Ok(exit_code)
     }
 
-    let config = args.value_of("CONFIG").expect("required set for config");
-    let config = std::fs::read_to_string(config).expect("Can't read config file");
-    let config = serde_json::from_str(&config).expect("Can't parse config");
+    let config = args.value_of("CONFIG").unwrap_or("console.json");
+    let config = std::fs::read_to_string(config)
+        .map_err(|err| ConsoleError::Config(format!("Can't read config file {}: {}", config, err)))?;
+    let config = serde_json::from_str(&config)
+        .map_err(|err| ConsoleError::Config(format!("Can't parse config: {}", err)))?;
     let timeout = match args.value_of("TIMEOUT") {
-        Some(timeout) => u64::from_str(timeout).expect("timeout must be set in microseconds"),
-        None => 0
+        Some(timeout) => {
+            let timeout = f64::from_str(timeout).ok().filter(|timeout| *timeout > 0.0)
+                .ok_or_else(|| ConsoleError::Params("timeout must be set in seconds".to_string()))?;
+            Some(Duration::from_secs_f64(timeout))
+        }
+        None => None
     };
-    let timeout = Duration::from_micros(timeout);
-    let mut client = ControlClient::connect(config).await.expect("Can't create client");
-    if let Some(commands) = args.values_of("COMMANDS") {
-        // batch mode - call commands and exit
+    let json = args.is_present("JSON");
+    let mut client = ControlClient::connect(config, timeout, json).await?;
+    let mut exit_code = 0;
+    if let Some(exporter) = args.subcommand_matches("exporter") {
+        let listen = exporter.value_of("LISTEN").unwrap_or_default();
+        let interval = exporter.value_of("INTERVAL").and_then(|interval| u64::from_str(interval).ok())
+            .filter(|interval| *interval > 0)
+            .ok_or_else(|| ConsoleError::Params("interval must be set in seconds".to_string()))?;
+        exit_code = client.run_exporter(listen, Duration::from_secs(interval)).await?;
+    } else if let Some(record) = args.subcommand_matches("record") {
+        let parse = |name: &str| record.value_of(name)
+            .and_then(|value| u64::from_str(value).ok())
+            .filter(|value| *value > 0)
+            .ok_or_else(|| ConsoleError::Params(format!("{} must be positive integer", name.to_lowercase())));
+        let recorder = StatsRecorder {
+            path: record.value_of("OUT").unwrap_or_default().to_string(),
+            max_size: parse("MAX_SIZE")? << 20,
+            keep: parse("KEEP")? as usize
+        };
+        exit_code = client.run_recorder(recorder, Duration::from_secs(parse("INTERVAL")?)).await?;
+    } else if let Some(path) = args.value_of("SCRIPT") {
+        // script stops on the first failed command as batch mode, unless --keep-going or set +e
+        exit_code = Script::new(!args.is_present("KEEP_GOING")).run(&mut client, path, json).await;
+    } else if let Some(commands) = args.values_of("COMMANDS") {
+        // batch mode - call commands and exit with code of the first failed one,
+        // the rest commands are skipped unless --keep-going is given
+        let keep_going = args.is_present("KEEP_GOING");
         for command in commands {
-            match client.command(command.trim_matches('\"')).await {
-                Ok((result, _)) => println!("{}", result),
-                Err(err) => println!("Error executing command: {}", err)
+            let command = command.trim_matches('\"');
+            let result = client.command(command).await;
+            print_result(command, &result, json);
+            if let Err(err) = &result {
+                if exit_code == 0 {
+                    exit_code = error_code(err);
+                }
+                if !keep_going {
+                    break
+                }
             }
-            tokio::time::sleep(timeout).await;
         }
     } else {
         // interactive mode
+        let history = args.value_of("HISTORY").map(|path| path.to_string())
+            .or_else(|| env::var("HOME").ok().map(|home| format!("{}/.console_history", home)));
+        let mut editor = rustyline::Editor::<ReplHelper>::new();
+        editor.set_helper(Some(ReplHelper::default()));
+        if let Some(history) = &history {
+            editor.load_history(history).ok();
+        }
+        let mut interrupts = interrupts();
         loop {
-            let mut line = String::default();
-            std::io::stdin().read_line(&mut line).expect("Can't read line from stdin");
-            match line.trim_end() {
+            // line editing blocks, so it runs out of runtime workers
+            let (line, returned) = tokio::task::spawn_blocking(move || {
+                let line = editor.readline("> ");
+                (line, editor)
+            }).await?;
+            editor = returned;
+            let line = match line {
+                Ok(line) => line,
+                // Ctrl-C drops the line being edited
+                Err(rustyline::error::ReadlineError::Interrupted) => continue,
+                Err(rustyline::error::ReadlineError::Eof) => break,
+                Err(err) => {
+                    println!("Can't read line: {}", err);
+                    break
+                }
+            };
+            match line.trim() {
                 "" => continue,
-                "quit" => break,
-                command => match client.command(command).await {
-                    Ok((result, _)) => println!("{}", result),
-                    Err(err) => println!("{}", err)
+                "quit" | "exit" => break,
+                command => {
+                    editor.add_history_entry(command);
+                    // Ctrl-C pressed before the command must not cancel it
+                    interrupts.borrow_and_update();
+                    let result = {
+                        let running = client.command(command);
+                        tokio::pin!(running);
+                        tokio::select! {
+                            result = &mut running => Some(result),
+                            _ = interrupts.changed() => None
+                        }
+                    };
+                    let result = match result {
+                        Some(result) => result,
+                        None => {
+                            // answer of the dropped query must not be taken for the answer to the next one,
+                            // as for timed out queries the connection is closed and the next query reconnects
+                            client.disconnect().await;
+                            Err(error!("command is cancelled by Ctrl-C"))
+                        }
+                    };
+                    print_result(command, &result, json);
+                    if let Some(helper) = editor.helper_mut() {
+                        helper.remember(command, &result);
+                    }
                 }
             }
         }
+        if let Some(history) = &history {
+            if let Err(err) = editor.save_history(history) {
+                log::warn!("Can't save history to {}: {}", history, err);
+            }
+        }
     }
     client.shutdown().await.ok();
+    Ok(exit_code)
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ELECTOR: &str = "-1:3333333333333333333333333333333333333333333333333333333333333333";
+
+    #[test]
+    fn test_parse_address() {
+        let elector = MsgAddressInt::from_str(ELECTOR).unwrap();
+        for address in [
+            ELECTOR,
+            // bounceable
+            "Ef8zMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzM0vF",
+            // non-bounceable
+            "Uf8zMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMxYA",
+            // bounceable, test only
+            "kf8zMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzM_BP",
+            // non-bounceable, test only
+            "0f8zMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzM62K",
+        ] {
+            assert_eq!(parse_address(address).unwrap(), elector, "{}", address);
+        }
+        // the same in base64 and base64url
+        let address = "EQAAAQIDBAUGBwgJCgsMDQ4PEBESExQVFhcYGRobHB0eHx2j";
+        let expected = MsgAddressInt::with_standart(None, 0, SliceData::from_raw((0..32).collect(), 256)).unwrap();
+        assert_eq!(parse_address(address).unwrap(), expected);
+        assert_eq!(parse_elector_wallet(ELECTOR).unwrap(), vec![0x33; 32]);
+    }
+
+    #[test]
+    fn test_parse_address_errors() {
+        // wrong checksum
+        assert!(parse_address("Ef8zMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzM0vG").is_err());
+        // wrong length
+        assert!(parse_address("Ef8zMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMz").is_err());
+        // basechain address does not fit elector
+        assert!(parse_elector_wallet("EQAAAQIDBAUGBwgJCgsMDQ4PEBESExQVFhcYGRobHB0eHx2j").is_err());
+    }
+
+    #[test]
+    fn test_keys_backup() {
+        let data = br#"{"keys":[],"secrets":{}}"#;
+        let backup = KeysBackup::encrypt(data, "password").unwrap();
+        assert_eq!(KeysBackup::decrypt(&backup, "password").unwrap(), data.to_vec());
+        assert!(KeysBackup::decrypt(&backup, "wrong password").is_err());
+
+        let mut damaged = backup.clone();
+        damaged["data"] = base64::encode(b"damaged").into();
+        assert!(KeysBackup::decrypt(&damaged, "password").is_err());
+    }
+
+    #[test]
+    fn test_keys_backup_parameters() {
+        let backup = KeysBackup::encrypt(b"keys", "password").unwrap();
+        let mut rounds = backup.clone();
+        rounds["rounds"] = u32::MAX.into();
+        assert!(KeysBackup::decrypt(&rounds, "password").is_err());
+        let mut salt = backup.clone();
+        salt["salt"] = base64::encode([0u8; 4]).into();
+        assert!(KeysBackup::decrypt(&salt, "password").is_err());
+        let mut nonce = backup;
+        nonce["nonce"] = base64::encode([0u8; 4]).into();
+        assert!(KeysBackup::decrypt(&nonce, "password").is_err());
+    }
+}